@@ -0,0 +1,39 @@
+#[macro_use]
+extern crate criterion;
+extern crate pls;
+
+use criterion::{Criterion, Throughput};
+use pls::{PlaylistElement, ElementLength};
+
+
+fn playlist(entries: u64) -> Vec<u8> {
+    let elems: Vec<_> = (0..entries)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/{:05} Track.mp3", i),
+                title: if i % 2 == 0 { Some(format!("A-F-R-O - Track {}", i)) } else { None },
+                len: if i % 3 == 0 { ElementLength::Unknown } else { ElementLength::Seconds(i) },
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write(&elems, &mut buf).unwrap();
+    buf
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for &(entries, name) in &[(10_000, "10k entries"), (50_000, "50k entries")] {
+        let data = playlist(entries);
+
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| pls::parse(&mut &data[..]).unwrap()));
+    }
+    group.finish();
+}
+
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,334 @@
+//! Formatting-preserving editing of whole playlist files
+
+
+use super::{PlaylistElement, ElementLength, WriteOptions, ParseError, parse, is_count_key, section_header, write_entry_keys};
+use std::io::{self, Write, Read};
+use std::cmp;
+
+
+/// A parsed playlist that remembers the original text, for editing with minimal changes
+///
+/// Only the `[playlist]` section is understood; everything else in the file is kept as-is.
+///
+/// On `write()`, entries that compare equal to what was parsed at the same position keep their original lines verbatim,
+/// including key order and spacing, as do all lines outside of entries.
+/// Changed entries are rewritten in place of the first line of the original entry, new entries are added after the last one,
+/// and the entry count is updated if it changed.
+/// Rewritten entries keep the original text of their `Length#` value, like `007`, if their length is unchanged.
+///
+/// Entries are identified by position, since `File#` indices are positional:
+/// removing or inserting an entry changes the indices of (and hence rewrites) all entries after it.
+///
+/// # Comments
+///
+/// `;` and `#` comment lines before the `[playlist]` header and inside the section are collected into `comments`,
+/// each anchored to where it goes:
+///
+///   * `None`: before the `[playlist]` header,
+///   * `Some(i)` with `i < entries.len()`: before the first line of the entry at (0-based) position `i`,
+///   * `Some(i)` with `i >= entries.len()`: at the end of the file.
+///
+/// When parsing, a comment is anchored to the entry whose key comes next,
+/// or to the end if none does (or `None` if it precedes the header).
+///
+/// Comments that are still in `comments` exactly as parsed stay on their original lines;
+/// removed ones disappear, and new (or changed) ones are written as `; text` lines at their anchor,
+/// one per line of the comment, so that no part of one can be misread as a key or section header.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{Document, ElementLength};
+/// let mut doc = pls::Document::parse(&mut &b"[playlist]\n\
+///                                           ; Exported by Unknown Player\n\
+///                                           File1 = Track 1.mp3\n\
+///                                           Title1 = Unknown Artist - Track 1\n\
+///                                           \n\
+///                                           File2=Track 2.mp3\n\
+///                                           \n\
+///                                           NumberOfEntries=2\n\
+///                                           Version=2\n"[..])
+///     .unwrap();
+/// assert_eq!(doc.comments, vec![(Some(0), "Exported by Unknown Player".to_string())]);
+///
+/// doc.entries[1].len = ElementLength::Seconds(420);
+/// doc.comments.push((Some(1), "Bought on vinyl".to_string()));
+/// doc.comments.push((None, "Favourites".to_string()));
+///
+/// let mut buf = Vec::new();
+/// doc.write(&mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "; Favourites\n\
+///             [playlist]\n\
+///             ; Exported by Unknown Player\n\
+///             File1 = Track 1.mp3\n\
+///             Title1 = Unknown Artist - Track 1\n\
+///             \n\
+///             ; Bought on vinyl\n\
+///             File2=Track 2.mp3\n\
+///             Length2=420\n\
+///             \n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Document {
+    /// The playlist's elements, to be edited freely
+    pub entries: Vec<PlaylistElement>,
+    /// Comment lines, without the leading `;` or `#` and whitespace, with their anchors
+    pub comments: Vec<(Option<usize>, String)>,
+    original: Vec<PlaylistElement>,
+    original_comments: Vec<(Option<usize>, String)>,
+    lines: Vec<Line>,
+    /// Index of the `[playlist]` line
+    header: usize,
+    newline: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct Line {
+    /// Including the line ending, if any
+    text: String,
+    kind: LineKind,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum LineKind {
+    Other,
+    /// A `File#`, `Title#`, `Length#`, or `Genre#` key of the entry with the specified index
+    Entry(u64),
+    /// One of the entry count keys; the `usize` is the length of the key
+    Count(usize),
+    /// The comment at the specified index in `original_comments`
+    Comment(usize),
+}
+
+
+impl Document {
+    /// Parse a playlist like `parse()`, keeping the original text around
+    pub fn parse<R: Read>(what: &mut R) -> Result<Document, ParseError> {
+        let mut data = Vec::new();
+        what.read_to_end(&mut data)?;
+        let text = String::from_utf8(data).map_err(|e| ParseError::Utf8(e.utf8_error().valid_up_to()))?;
+
+        let entries = parse(&mut text.as_bytes())?;
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+        let mut header = None;
+        let mut in_playlist = false;
+        let mut comments = vec![];
+        let lines: Vec<_> = text.split_inclusive('\n')
+            .enumerate()
+            .map(|(i, l)| {
+                let content = if i == 0 { l.trim_start_matches('\u{FEFF}') } else { l }.trim();
+                let kind = if content.starts_with('[') {
+                    in_playlist = section_header(content).map(|s| s.eq_ignore_ascii_case("playlist")).unwrap_or(false);
+                    if in_playlist && header.is_none() {
+                        header = Some(i);
+                    }
+                    LineKind::Other
+                } else if in_playlist || header.is_none() {
+                    if content.starts_with(';') || content.starts_with('#') {
+                        comments.push((if header.is_none() { None } else { Some(entries.len()) }, content[1..].trim_start().to_string()));
+                        LineKind::Comment(comments.len() - 1)
+                    } else if header.is_none() {
+                        LineKind::Other
+                    } else {
+                        // Keys past the entry count aren't part of any entry
+                        match classify(content) {
+                            LineKind::Entry(idx) if idx > entries.len() as u64 => LineKind::Other,
+                            kind => kind,
+                        }
+                    }
+                } else {
+                    LineKind::Other
+                };
+                Line {
+                    text: l.to_string(),
+                    kind,
+                }
+            })
+            .collect();
+
+        // Anchor the comments in the section to the entry following them
+        let mut next_entry = entries.len();
+        for line in lines.iter().rev() {
+            match line.kind {
+                LineKind::Entry(idx) => next_entry = idx as usize - 1,
+                LineKind::Comment(k) => {
+                    if let Some(ref mut anchor) = comments[k].0 {
+                        *anchor = next_entry;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Document {
+            original: entries.clone(),
+            entries,
+            original_comments: comments.clone(),
+            comments,
+            lines,
+            header: header.unwrap_or(0),
+            newline,
+        })
+    }
+
+    /// Write the playlist, changing only what's necessary to reflect the edits to `entries` and `comments`
+    pub fn write<W: Write>(&self, to: &mut W) -> io::Result<()> {
+        let last_entry_line = self.lines.iter().rposition(|l| matches!(l.kind, LineKind::Entry(_)));
+        let append_after = last_entry_line.unwrap_or(self.header);
+
+        // Match up unchanged comments, in order, the rest go at their anchors
+        let mut kept = vec![false; self.original_comments.len()];
+        let mut new = vec![];
+        for comment in &self.comments {
+            match (0..kept.len()).find(|&k| !kept[k] && self.original_comments[k] == *comment) {
+                Some(k) => kept[k] = true,
+                None => new.push(comment),
+            }
+        }
+        let end = self.entries.len();
+        let anchored = |anchor: Option<usize>| new.iter().filter(move |c| c.0.map(|i| cmp::min(i, end)) == anchor).map(|c| &c.1);
+
+        let mut started = vec![false; self.entries.len()];
+        let mut at_line_start = true;
+        for (i, line) in self.lines.iter().enumerate() {
+            if i == self.header {
+                self.write_comments(anchored(None), to)?;
+            }
+
+            match line.kind {
+                LineKind::Count(key_len) if self.entries.len() != self.original.len() => {
+                    write!(to, "{}={}{}", &line.text.trim_start()[..key_len], self.entries.len(), self.newline)?;
+                    at_line_start = true;
+                }
+                LineKind::Comment(k) if !kept[k] => {}
+                LineKind::Entry(idx) if idx as usize > self.entries.len() => {}
+                LineKind::Entry(idx) => {
+                    let pos = idx as usize - 1;
+                    let first = !started[pos];
+                    if first {
+                        started[pos] = true;
+                        self.write_comments(anchored(Some(pos)), to)?;
+                    }
+
+                    if self.original[pos] == self.entries[pos] {
+                        to.write_all(line.text.as_bytes())?;
+                        at_line_start = line.text.ends_with('\n');
+                    } else if first {
+                        self.write_entry(idx, &self.entries[pos], to)?;
+                        at_line_start = true;
+                    }
+                }
+                _ => {
+                    to.write_all(line.text.as_bytes())?;
+                    at_line_start = line.text.ends_with('\n');
+                }
+            }
+
+            if i == append_after && self.entries.len() > self.original.len() {
+                if !at_line_start {
+                    to.write_all(self.newline.as_bytes())?;
+                    at_line_start = true;
+                }
+                // Separate from the preceding entry, or from the following keys if there are no entries before
+                for (pos, elem) in self.entries.iter().enumerate().skip(self.original.len()) {
+                    if last_entry_line.is_some() {
+                        to.write_all(self.newline.as_bytes())?;
+                    }
+                    self.write_comments(anchored(Some(pos)), to)?;
+                    self.write_entry(pos as u64 + 1, elem, to)?;
+                    if last_entry_line.is_none() {
+                        to.write_all(self.newline.as_bytes())?;
+                    }
+                }
+            }
+        }
+
+        let mut trailing = anchored(Some(end)).peekable();
+        if trailing.peek().is_some() && !at_line_start {
+            to.write_all(self.newline.as_bytes())?;
+        }
+        self.write_comments(trailing, to)
+    }
+
+    fn write_entry<W: Write>(&self, idx: u64, what: &PlaylistElement, to: &mut W) -> io::Result<()> {
+        let mut buf = Vec::new();
+        write_entry_keys(idx, what, &WriteOptions::default(), &mut buf)?;
+
+        // Only the whole Length# line is swapped, not the same text inside another value
+        let length = match (self.original_length(idx, what), what.len) {
+            (Some(original), ElementLength::Seconds(secs)) => Some((format!("Length{}={}", idx, secs), original)),
+            _ => None,
+        };
+        for line in String::from_utf8_lossy(&buf).split_terminator('\n') {
+            match length {
+                Some((ref written, original)) if line == written => write!(to, "Length{}={}{}", idx, original, self.newline)?,
+                _ => write!(to, "{}{}", line, self.newline)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The original `Length#` value of the entry with the specified index, if `what` has the same length as what was parsed
+    fn original_length(&self, idx: u64, what: &PlaylistElement) -> Option<&str> {
+        if self.original.get(idx as usize - 1)?.len != what.len {
+            return None;
+        }
+
+        // Like when parsing, the last of duplicate keys wins
+        self.lines
+            .iter()
+            .rev()
+            .filter(|l| l.kind == LineKind::Entry(idx))
+            .filter_map(|l| {
+                let content = l.text.trim();
+                let i = content.find(&['=', ':'][..])?;
+                if content[..i].trim_end().starts_with("Length") {
+                    Some(content[i + 1..].trim())
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+
+    fn write_comments<'c, I: Iterator<Item = &'c String>, W: Write>(&self, comments: I, to: &mut W) -> io::Result<()> {
+        for line in comments.flat_map(|c| c.split('\n')).map(|l| l.strip_suffix('\r').unwrap_or(l)) {
+            if line.is_empty() {
+                write!(to, ";{}", self.newline)?;
+            } else {
+                write!(to, "; {}{}", line, self.newline)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Figure out which key the `[playlist]` section line `content` (already trimmed) is
+fn classify(content: &str) -> LineKind {
+    let key = match content.find(&['=', ':'][..]) {
+        Some(i) => content[..i].trim_end(),
+        None => return LineKind::Other,
+    };
+
+    if is_count_key(key) {
+        return LineKind::Count(key.len());
+    }
+
+    // Only the canonical spelling of an index (no sign, no leading zeroes) is looked up when parsing
+    for prefix in &["File", "Title", "Length", "Genre"] {
+        if let Some(idx) = key.strip_prefix(prefix) {
+            if !idx.starts_with('0') && idx.bytes().all(|b| b.is_ascii_digit()) {
+                if let Ok(idx) = idx.parse() {
+                    return LineKind::Entry(idx);
+                }
+            }
+        }
+    }
+
+    LineKind::Other
+}
@@ -21,16 +21,19 @@
 //!                path: "Track 1.mp3".to_string(),
 //!                title: Some("Unknown Artist - Track 1".to_string()),
 //!                len: ElementLength::Unknown,
+//!                genre: None,
 //!            },
 //!            PlaylistElement {
 //!                path: "Track 2.mp3".to_string(),
 //!                title: None,
 //!                len: ElementLength::Seconds(420),
+//!                genre: None,
 //!            },
 //!            PlaylistElement {
 //!                path: "Track 3.mp3".to_string(),
 //!                title: None,
 //!                len: ElementLength::Unknown,
+//!                genre: None,
 //!            }]);
 //! ```
 //!
@@ -43,16 +46,19 @@
 //!                path: "Track 1.mp3".to_string(),
 //!                title: Some("Unknown Artist - Track 1".to_string()),
 //!                len: ElementLength::Unknown,
+//!                genre: None,
 //!            },
 //!            PlaylistElement {
 //!                path: "Track 2.mp3".to_string(),
 //!                title: None,
 //!                len: ElementLength::Seconds(420),
+//!                genre: None,
 //!            },
 //!            PlaylistElement {
 //!                path: "Track 3.mp3".to_string(),
 //!                title: None,
 //!                len: ElementLength::Unknown,
+//!                genre: None,
 //!            }],
 //!            &mut buf).unwrap();
 //! assert_eq!(String::from_utf8(buf).unwrap(),
@@ -71,15 +77,54 @@
 
 
 extern crate ini as _ini;
+#[cfg(feature = "url")]
+extern crate url;
+#[cfg(feature = "encoding")]
+extern crate encoding_rs;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
 
-use std::io::{self, Write, Read};
+mod ops;
+mod options;
+mod document;
+mod writer;
+mod m3u;
+
+pub use ops::{PlaylistStats, enrich_from, eq_normalized, fill_lengths, filter_out, local_paths, from_pairs, from_tags, group_by_dir, move_entry, normalize,
+              paths, same_set, search, stats, swap_entries, to_bare_paths, to_file_urls};
+#[cfg(feature = "url")]
+pub use ops::urls;
+#[cfg(feature = "encoding")]
+pub use ops::repair_mojibake;
+pub use options::{EntryKey, LengthFormat, LineEnding, NormalizeOptions, ParseOptions, WriteOptions};
+pub use document::Document;
+pub use writer::Writer;
+pub use m3u::{transcode_m3u_to_pls, write_m3u};
+
+use std::io::{self, BufWriter, Write, Read};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::ffi::OsString;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::error::Error as ErrorT;
-use std::num::ParseIntError;
+use std::num::{ParseIntError, IntErrorKind};
+use std::convert::TryFrom;
+use std::str::{self, FromStr};
+use std::borrow::Cow;
+use std::ops::{ControlFlow, Range};
+use std::{cmp, iter};
+use std::time::Duration;
 use _ini::ini;
 use std::fmt;
+#[cfg(feature = "encoding")]
+use encoding_rs::{Encoding, EncoderResult};
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
 
 
-/// A single element of a playlist
+/// A single element of a playlist, owning its strings, as produced by parsing
 ///
 /// # Examples
 ///
@@ -105,6 +150,7 @@ use std::fmt;
 /// #                path: "Track 1.mp3".to_string(),
 /// #                title: Some("Unknown Artist - Track 1".to_string()),
 /// #                len: ElementLength::Seconds(420),
+/// #                genre: None,
 /// #            }]);
 /// ```
 ///
@@ -123,17 +169,70 @@ use std::fmt;
 ///                path: "Track 1.mp3".to_string(),
 ///                title: Some("Unknown Artist - Track 1".to_string()),
 ///                len: ElementLength::Seconds(420),
+///                genre: None,
 ///            }],
 ///            &mut File::create("Unknown Artist.pls")).unwrap();
 /// ```
+pub type PlaylistElement = PlaylistElementGen<String>;
+
+/// A single element of a playlist, borrowing its strings, for writing out borrowed data without copying it into `String`s first
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElementRef, ElementLength};
+/// let paths = ["Track 1.mp3", "Track 2.mp3"];
+/// let elements: Vec<_> = paths.iter()
+///     .map(|p| {
+///         PlaylistElementRef {
+///             path: p,
+///             title: None,
+///             len: ElementLength::Unknown,
+///             genre: None,
+///         }
+///     })
+///     .collect();
+///
+/// let mut buf = Vec::new();
+/// pls::write(&elements, &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             \n\
+///             File2=Track 2.mp3\n\
+///             \n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+/// ```
+pub type PlaylistElementRef<'s> = PlaylistElementGen<&'s str>;
+
+/// A single element of a playlist, with its strings stored as `S`
+///
+/// Use it through `PlaylistElement` or `PlaylistElementRef`.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct PlaylistElement {
+pub struct PlaylistElementGen<S> {
     /// Path specified in the `File#` key, unconstrained
-    pub path: String,
+    ///
+    /// Empty if the key was omitted and `ParseOptions::allow_missing_file` was set
+    pub path: S,
     /// Title specified by the `Title#` key or `None` if omitted
-    pub title: Option<String>,
+    pub title: Option<S>,
     /// Length specified by the `Length#` key or `Unknown` if omitted
     pub len: ElementLength,
+    /// Genre specified by the `Genre#` key or `None` if omitted, as found in internet radio directory listings
+    pub genre: Option<S>,
+}
+
+/// A whole playlist, with its own name, as returned by `parse_full()`
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct Playlist {
+    /// Name of the playlist itself, specified by the `PlaylistName` key, or a `Title` key without an index, or `None` if neither
+    /// is present
+    ///
+    /// If both are present, `PlaylistName` wins.
+    pub name: Option<String>,
+    /// The playlist's elements, in order
+    pub entries: Vec<PlaylistElement>,
 }
 
 /// Playlist element's length
@@ -147,19 +246,120 @@ pub enum ElementLength {
     Unknown,
 }
 
+/// What a playlist element's path looks like, as returned by `PlaylistElement::path_kind()`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PathKind {
+    /// A URL: `scheme://` followed by anything, or `file:` followed by anything
+    Url,
+    /// A Windows UNC path, starting with `\\`, like `\\Server\Music\Track 1.mp3`
+    Unc,
+    /// A Windows path starting with a drive letter, either absolute (`C:\Track 1.mp3`) or drive-relative (`C:Track 1.mp3`)
+    Drive,
+    /// Any other path, absolute or relative
+    Local,
+}
+
+/// What a playlist element points to, by its extension, as returned by `PlaylistElement::media_kind()`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MediaKind {
+    /// `aac`, `aif`, `aiff`, `ape`, `flac`, `m4a`, `mka`, `mp2`, `mp3`, `oga`, `ogg`, `opus`, `wav`, `wma`, or `wv`
+    Audio,
+    /// `avi`, `flv`, `m4v`, `mkv`, `mov`, `mp4`, `mpeg`, `mpg`, `ogv`, `webm`, or `wmv`
+    Video,
+    /// `asx`, `m3u`, `m3u8`, `pls`, `wpl`, or `xspf`
+    Playlist,
+    /// Any other extension, or none at all, as with most stream URLs
+    Unknown,
+}
+
+/// What writing a playlist would produce, as returned by `validate_for_write()`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WritePreview {
+    /// Length of the playlist, in bytes
+    pub bytes: usize,
+    /// Amount of entries written
+    pub entries: u64,
+    /// Problems with the elements, in order
+    pub warnings: Vec<WriteWarning>,
+}
+
+/// A problem with an element that doesn't stop it from being written, but makes the playlist not read back as intended
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum WriteWarning {
+    /// The path is empty or only whitespace, which most players skip or choke on
+    EmptyPath {
+        /// The `File#` key it's written to
+        key: String,
+    },
+    /// The value contains a line break, so the rest of it will be read as another key, or break parsing entirely
+    LineBreak {
+        /// The key it's written to, like `Title3`
+        key: String,
+    },
+    /// The value will read back differently, since it has leading or trailing whitespace or trailing NULs (which are trimmed),
+    /// contains a `;` or `#` (which start a comment), or starts with a quote (which is stripped)
+    Altered {
+        /// The key it's written to, like `Title3`
+        key: String,
+    },
+}
+
 /// All ways parsing can fail
 #[derive(Debug)]
 pub enum ParseError {
-    /// Specified version was not `2`
+    /// Specified version was not `2`, or not one of `ParseOptions::allowed_versions`
     InvalidVersion(u64),
-    /// The whole `[playlist]` section's missing
+    /// The input was empty, or only whitespace, like an empty HTTP response body
+    Empty,
+    /// The whole `[playlist]` section's missing (or the one named by `ParseOptions::section_name`)
     MissingPlaylistSection,
     /// Some required key is missing
-    MissingKey(String),
+    MissingKey {
+        /// The key, like `File3`, or the alternatives, separated by `|`, like `NumberOfEntries|numberofentries|NumberOfEvents`
+        key: String,
+        /// The index of the entry the key is of, if it's one of an entry's
+        entry: Option<u64>,
+    },
+    /// More than one entry count key (`NumberOfEntries` or `NumberOfEvents`, in any capitalisation) was specified,
+    /// with differing values
+    ///
+    /// Only returned with `ParseOptions::reject_conflicting_counts`.
+    ConflictingCountKeys,
     /// An integer was not actually an integer
-    InvalidInteger(ParseIntError),
-    /// Other `.ini` parsing errors
+    InvalidInteger {
+        /// The key whose value it was, like `Length3`
+        key: String,
+        /// The value, as it appeared in the playlist
+        value: String,
+        /// Why it didn't parse
+        source: ParseIntError,
+    },
+    /// A `Length#` value was an integer too big to fit in a `u64`
+    ///
+    /// Not returned with `ParseOptions::clamp_overflowing_lengths`.
+    LengthOverflow {
+        /// The key whose value it was, like `Length3`
+        key: String,
+        /// The value, as it appeared in the playlist
+        value: String,
+    },
+    /// The input was longer than the specified amount of bytes
+    ///
+    /// Only returned with `ParseOptions::max_bytes`.
+    TooLarge(usize),
+    /// The input isn't valid UTF-8, first going wrong at the specified byte offset
+    ///
+    /// This usually means it's in a legacy encoding, like Windows-1252, which `parse_with_encoding()` reads.
+    Utf8(usize),
+    /// Other `.ini` syntax errors
     Ini(ini::Error),
+    /// Reading the input, or opening or writing a file failed
+    ///
+    /// The input is read in full before it's parsed, so an error from the reader, like a reset connection,
+    /// always ends up here, and never looks like a syntax error.
+    ///
+    /// Only the kind of the error is kept, so that this can be compared and cloned exactly, like all other variants.
+    Io(io::ErrorKind),
 }
 
 
@@ -167,6 +367,17 @@ pub enum ParseError {
 ///
 /// The parser is very lenient and allows pretty much everything as long as the required stuff's in.
 ///
+/// The `[playlist]` section is matched case-insensitively, so `[Playlist]` works too.
+///
+/// Keys end at the first `=`, so values (titles, URLs with queries) may themselves contain `=`.
+/// Keys are looked up by name, so their order doesn't matter, within an entry or across entries.
+/// The entry count is read from `NumberOfEntries`, or `NumberOfEvents`, as some radio stations write, in any capitalisation.
+/// Whitespace (spaces or tabs) around keys and values is ignored, so hand-edited `File1 = Track 1.mp3` works too,
+/// as are NUL bytes at the end of values, which tools writing fixed-size buffers pad them with.
+/// Backslashes aren't escapes, so Windows paths like `C:\Music\track.mp3` are read as-is.
+/// The `[playlist]` header may be followed by whitespace and a comment, like `[playlist] ; exported by Unknown Player`.
+/// A leading UTF-8 byte order mark is ignored.
+///
 /// # Examples
 ///
 /// ```
@@ -186,191 +397,3314 @@ pub enum ParseError {
 ///                path: "Track 1.mp3".to_string(),
 ///                title: Some("Unknown Artist - Track 1".to_string()),
 ///                len: ElementLength::Unknown,
+///                genre: None,
 ///            },
 ///            PlaylistElement {
 ///                path: "Track 2.mp3".to_string(),
 ///                title: None,
 ///                len: ElementLength::Seconds(420),
+///                genre: None,
 ///            },
 ///            PlaylistElement {
 ///                path: "Track 3.mp3".to_string(),
 ///                title: None,
 ///                len: ElementLength::Unknown,
+///                genre: None,
 ///            }]);
 /// ```
 pub fn parse<R: Read>(what: &mut R) -> Result<Vec<PlaylistElement>, ParseError> {
-    let p = try!(ini::Ini::read_from(what));
-    let play = try!(p.section(Some("playlist")).ok_or(ParseError::MissingPlaylistSection));
-
-    if let Some(v) = play.get("Version") {
-        let v = try!(v.parse());
-        if v != 2 {
-            return Err(ParseError::InvalidVersion(v));
-        }
-    }
-
-    // Some major radio stations have malformed pls files, handle without error:
-    // "numberofentries" http://newmedia.kcrw.com/legacy/pls/kcrwsimulcast.pls
-    // "NumberOfEvents" http://www.abc.net.au/res/streaming/audio/mp3/classic_fm.pls
-    if let Some(e) = play.get("NumberOfEntries").or_else(|| play.get("numberofentries")).or_else(|| play.get("NumberOfEvents")) {
-        let e: u64 = try!(e.parse());
-        let mut elems = Vec::with_capacity(e as usize);
-        for i in 1..e + 1 {
-            elems.push(PlaylistElement {
-                path: try!(play.get(&format!("File{}", i)).ok_or_else(|| ParseError::MissingKey(format!("File{}", i)))).clone(),
-                title: play.get(&format!("Title{}", i)).cloned(),
-                len: try!(ElementLength::parse(play.get(&format!("Length{}", i)))),
-            })
+    parse_with(what, &ParseOptions::default())
+}
+
+/// Parse a playlist, configured by the specified options
+///
+/// `parse()` is this with the default options, and so, after decoding, are `parse_str()`, `parse_bytes()`, and the like.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, ParseOptions};
+/// assert_eq!(pls::parse_with(&mut &b"[playlist]\n\
+///                                    Title1=Unknown Artist - Now Playing\n\
+///                                    Length1=420\n\
+///                                    \n\
+///                                    NumberOfEntries=1\n"[..],
+///                            &ParseOptions { allow_missing_file: true, ..ParseOptions::default() }).unwrap(),
+///            vec![PlaylistElement {
+///                path: "".to_string(),
+///                title: Some("Unknown Artist - Now Playing".to_string()),
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            }]);
+/// ```
+pub fn parse_with<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<Vec<PlaylistElement>, ParseError> {
+    ParseIter::new(what, opts)?.collect_elements()
+}
+
+/// Parse a playlist like `parse()`, but read every entry whose `File#` key is present, in numeric order, skipping gaps
+///
+/// This is `parse_with()` without `ParseOptions::require_contiguous_indices` or `ParseOptions::require_number_of_entries`,
+/// for files that had entries removed without renumbering the rest, or whose entry count is off or missing.
+/// An entry count that's there still has to be valid.
+///
+/// # Examples
+///
+/// ```
+/// let data = b"[playlist]\n\
+///              File1=Track 1.mp3\n\
+///              File3=Track 3.mp3\n\
+///              Title3=Unknown Artist - Track 3\n\
+///              File4=Track 4.mp3\n\
+///              NumberOfEntries=3\n";
+/// assert_eq!(pls::parse(&mut &data[..]),
+///            Err(pls::ParseError::MissingKey {
+///                key: "File2".to_string(),
+///                entry: Some(2),
+///            }));
+///
+/// let elems = pls::parse_lenient(&mut &data[..]).unwrap();
+/// assert_eq!(elems.iter().map(|e| &e.path[..]).collect::<Vec<_>>(), vec!["Track 1.mp3", "Track 3.mp3", "Track 4.mp3"]);
+/// assert_eq!(elems[1].title, Some("Unknown Artist - Track 3".to_string()));
+/// ```
+pub fn parse_lenient<R: Read>(what: &mut R) -> Result<Vec<PlaylistElement>, ParseError> {
+    parse_with(what,
+               &ParseOptions {
+                   require_contiguous_indices: false,
+                   require_number_of_entries: false,
+                   ..ParseOptions::default()
+               })
+}
+
+/// Parse a playlist like `parse_with()`, but pair each element with the 1-based `File#` index it was read from
+///
+/// This lets diagnostics refer back to the keys in the source file, even after the elements are renumbered on `write()`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, ParseOptions};
+/// assert_eq!(pls::parse_indexed(&mut &b"[playlist]\n\
+///                                       File1=Track 1.mp3\n\
+///                                       File2=Track 2.mp3\n\
+///                                       Length2=420\n\
+///                                       NumberOfEntries=2\n"[..],
+///                               &ParseOptions::default()).unwrap(),
+///            vec![(1,
+///                  PlaylistElement {
+///                      path: "Track 1.mp3".to_string(),
+///                      title: None,
+///                      len: ElementLength::Unknown,
+///                      genre: None,
+///                  }),
+///                 (2,
+///                  PlaylistElement {
+///                      path: "Track 2.mp3".to_string(),
+///                      title: None,
+///                      len: ElementLength::Seconds(420),
+///                      genre: None,
+///                  })]);
+/// ```
+pub fn parse_indexed<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<Vec<(usize, PlaylistElement)>, ParseError> {
+    let mut iter = ParseIter::new(what, opts)?;
+
+    let mut elems = Vec::with_capacity(cmp::min(iter.len(), iter.section.len()));
+    while let Some(elem) = iter.next_indexed() {
+        let (i, elem) = elem?;
+        if iter.opts.coalesce_adjacent_duplicates && elems.last().map(|l: &(usize, PlaylistElement)| l.1.path == elem.path).unwrap_or(false) {
+            continue;
         }
-        Ok(elems)
-    } else {
-        Err(ParseError::MissingKey("NumberOfEntries|numberofentries|NumberOfEvents".to_string()))
+        elems.push((i as usize, elem));
     }
+    Ok(elems)
 }
 
-/// Write a playlist to the specified output stream
+/// Parse a playlist like `parse_with()`, along with its name
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{Playlist, PlaylistElement, ElementLength, ParseOptions};
+/// assert_eq!(pls::parse_full(&mut &b"[playlist]\n\
+///                                    PlaylistName=Favourites\n\
+///                                    File1=Track 1.mp3\n\
+///                                    NumberOfEntries=1\n"[..],
+///                            &ParseOptions::default()).unwrap(),
+///            Playlist {
+///                name: Some("Favourites".to_string()),
+///                entries: vec![PlaylistElement {
+///                                  path: "Track 1.mp3".to_string(),
+///                                  title: None,
+///                                  len: ElementLength::Unknown,
+///                                  genre: None,
+///                              }],
+///            });
+/// ```
+pub fn parse_full<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<Playlist, ParseError> {
+    let iter = ParseIter::new(what, opts)?;
+    let name = iter.section.get("PlaylistName").or_else(|| iter.section.get("Title")).cloned();
+
+    Ok(Playlist {
+        name,
+        entries: iter.collect_elements()?,
+    })
+}
+
+/// Parse a playlist, producing the elements lazily
+///
+/// The whole input is read and validated up-front, but each `PlaylistElement` is only constructed when the iterator reaches
+/// it, so stopping early (e.g. with `.take(n)` or `.find()`) skips the remaining entries entirely, including their errors.
+///
+/// Each item is the result of parsing that entry; the iterator carries on past errors.
 ///
 /// # Examples
 ///
 /// ```
 /// # use pls::{PlaylistElement, ElementLength};
-/// let mut buf = Vec::new();
-/// pls::write(&[PlaylistElement {
+/// let mut iter = pls::parse_iter(&mut &b"[playlist]\n\
+///                                       File1=Track 1.mp3\n\
+///                                       \n\
+///                                       Length2=Missing file, bogus length\n\
+///                                       \n\
+///                                       NumberOfEntries=2\n"[..]).unwrap();
+/// assert_eq!(iter.len(), 2);
+/// assert_eq!(iter.next(),
+///            Some(Ok(PlaylistElement {
 ///                path: "Track 1.mp3".to_string(),
-///                title: Some("Unknown Artist - Track 1".to_string()),
-///                len: ElementLength::Unknown,
-///            },
-///            PlaylistElement {
-///                path: "Track 2.mp3".to_string(),
-///                title: None,
-///                len: ElementLength::Seconds(420),
-///            },
-///            PlaylistElement {
-///                path: "Track 3.mp3".to_string(),
 ///                title: None,
 ///                len: ElementLength::Unknown,
-///            }],
-///            &mut buf).unwrap();
-/// assert_eq!(String::from_utf8(buf).unwrap(),
-///            "[playlist]\n\
-///             File1=Track 1.mp3\n\
-///             Title1=Unknown Artist - Track 1\n\
-///             \n\
-///             File2=Track 2.mp3\n\
-///             Length2=420\n\
-///             \n\
-///             File3=Track 3.mp3\n\
-///             \n\
-///             NumberOfEntries=3\n\
-///             Version=2\n")
+///                genre: None,
+///            })));
+/// assert!(iter.next().unwrap().is_err());
+/// assert_eq!(iter.next(), None);
 /// ```
-pub fn write<'i, I: IntoIterator<Item = &'i PlaylistElement>, W: Write>(what: I, to: &mut W) -> io::Result<()> {
-    try!(writeln!(to, "[playlist]"));
+pub fn parse_iter<R: Read>(what: &mut R) -> Result<ParseIter, ParseError> {
+    ParseIter::new(what, &ParseOptions::default())
+}
 
-    let mut ent = 0u64;
-    for (i, &PlaylistElement { ref path, ref title, ref len }) in what.into_iter().enumerate() {
-        try!(writeln!(to, "File{}={}", i + 1, path));
+/// Parse a playlist lazily like `parse_iter()`, configured by the specified options
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let iter = pls::parse_iter_with(&mut &b"[playlist]\n\
+///                                        File1=Track 1.mp3\n\
+///                                        File3=Track 3.mp3\n\
+///                                        NumberOfEntries=3\n"[..],
+///                                 &ParseOptions { require_contiguous_indices: false, ..ParseOptions::default() })
+///     .unwrap();
+/// assert_eq!(iter.missing_indices().collect::<Vec<_>>(), vec![2]);
+/// assert_eq!(iter.map(|e| e.unwrap().path).collect::<Vec<_>>(), vec!["Track 1.mp3", "Track 3.mp3"]);
+/// ```
+pub fn parse_iter_with<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<ParseIter, ParseError> {
+    ParseIter::new(what, opts)
+}
 
-        if let Some(title) = title.as_ref() {
-            try!(writeln!(to, "Title{}={}", i + 1, title));
-        }
+/// Parse a playlist, handing each element to the callback in order instead of collecting them
+///
+/// Returning `ControlFlow::Break` from the callback stops parsing right away, successfully;
+/// like with `parse_iter()`, the entries after that aren't constructed, so their errors aren't reported.
+///
+/// An error in any entry reached stops parsing and is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::ops::{ControlFlow, Range};
+/// let mut paths = vec![];
+/// pls::parse_visit(&mut &b"[playlist]\n\
+///                          File1=Track 1.mp3\n\
+///                          File2=Track 2.mp3\n\
+///                          Length3=Missing file, bogus length\n\
+///                          NumberOfEntries=3\n"[..],
+///                  |elem| {
+///                      paths.push(elem.path);
+///                      if paths.len() == 2 {
+///                          ControlFlow::Break(())
+///                      } else {
+///                          ControlFlow::Continue(())
+///                      }
+///                  })
+///     .unwrap();
+/// assert_eq!(paths, vec!["Track 1.mp3", "Track 2.mp3"]);
+/// ```
+pub fn parse_visit<R: Read, F: FnMut(PlaylistElement) -> ControlFlow<()>>(what: &mut R, f: F) -> Result<(), ParseError> {
+    parse_visit_with(what, &ParseOptions::default(), f)
+}
 
-        if let ElementLength::Seconds(s) = *len {
-            try!(writeln!(to, "Length{}={}", i + 1, s));
+/// Parse a playlist, handing each element to `f` until it breaks, like `parse_visit()`, configured by the specified options
+///
+/// Elements `ParseOptions::coalesce_adjacent_duplicates` collapses aren't visited, like `parse_with()` leaves them out.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// # use std::ops::ControlFlow;
+/// let mut paths = vec![];
+/// pls::parse_visit_with(&mut &b"[playlist]\n\
+///                               File1=Track 1.mp3\n\
+///                               File3=Track 3.mp3\n\
+///                               NumberOfEntries=3\n"[..],
+///                       &ParseOptions::default().require_contiguous_indices(false),
+///                       |elem| {
+///                           paths.push(elem.path);
+///                           ControlFlow::Continue(())
+///                       })
+///     .unwrap();
+/// assert_eq!(paths, vec!["Track 1.mp3", "Track 3.mp3"]);
+/// ```
+pub fn parse_visit_with<R: Read, F: FnMut(PlaylistElement) -> ControlFlow<()>>(what: &mut R, opts: &ParseOptions, mut f: F) -> Result<(), ParseError> {
+    let mut last_path = None;
+    for elem in ParseIter::new(what, opts)? {
+        let elem = elem?;
+        if opts.coalesce_adjacent_duplicates {
+            if last_path.as_ref() == Some(&elem.path) {
+                continue;
+            }
+            last_path = Some(elem.path.clone());
         }
 
-        try!(writeln!(to, ""));
-        ent += 1;
+        if f(elem).is_break() {
+            break;
+        }
     }
-
-    try!(writeln!(to, "NumberOfEntries={}", ent));
-    try!(writeln!(to, "Version=2"));
-
     Ok(())
 }
 
+/// Parse a playlist from a string
+///
+/// A leading byte order mark is ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(pls::parse_str("\u{FEFF}[playlist]\n\
+///                            File1=Track 1.mp3\n\
+///                            NumberOfEntries=1\n").unwrap(),
+///            vec![PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            }]);
+/// ```
+pub fn parse_str(what: &str) -> Result<Vec<PlaylistElement>, ParseError> {
+    parse_str_with(what, &ParseOptions::default())
+}
 
-impl ElementLength {
-    fn parse<S: AsRef<str>>(what: Option<S>) -> Result<ElementLength, ParseError> {
-        if let Some(what) = what {
-            let what = what.as_ref();
-            if what == "-1" {
-                Ok(ElementLength::Unknown)
-            } else {
-                Ok(ElementLength::Seconds(try!(what.parse())))
-            }
-        } else {
-            Ok(ElementLength::Unknown)
-        }
-    }
+/// Parse a playlist from a string, like `parse_str()`, configured by the specified options
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let data = "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             NumberOfEntries=1\n\
+///             Version=3\n";
+/// assert!(pls::parse_str(data).is_err());
+/// assert_eq!(pls::paths(&pls::parse_str_with(data, &ParseOptions::default().allowed_versions(None)).unwrap()), ["Track 1.mp3"]);
+/// ```
+pub fn parse_str_with(what: &str, opts: &ParseOptions) -> Result<Vec<PlaylistElement>, ParseError> {
+    parse_with(&mut what.as_bytes(), opts)
 }
 
+/// Parse a playlist from raw bytes, like an HTTP response body
+///
+/// The encoding is detected from the byte order mark: UTF-16 (either endianness) is transcoded, with unpaired surrogates
+/// replaced by U+FFFD; anything else is treated as UTF-8, with the UTF-8 BOM, if any, ignored,
+/// failing with `ParseError::Utf8` if it isn't.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let body: Vec<u8> = "[playlist]\nFile1=Track 1.mp3\nNumberOfEntries=1\n"
+///     .encode_utf16()
+///     .fold(vec![0xFF, 0xFE], |mut acc, c| {
+///         acc.push(c as u8);
+///         acc.push((c >> 8) as u8);
+///         acc
+///     });
+/// assert_eq!(pls::parse_bytes(&body).unwrap(),
+///            vec![PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            }]);
+/// ```
+pub fn parse_bytes(what: &[u8]) -> Result<Vec<PlaylistElement>, ParseError> {
+    parse_bytes_with(what, &ParseOptions::default())
+}
 
-impl From<ini::Error> for ParseError {
-    fn from(e: ini::Error) -> ParseError {
-        ParseError::Ini(e)
+/// Parse a playlist from raw bytes, like `parse_bytes()`, configured by the specified options
+///
+/// `ParseOptions::max_bytes` limits the length of `what` as-is, before any transcoding.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{ParseError, ParseOptions};
+/// let body = b"[playlist]\nFile1=Track 1.mp3\nNumberOfEntries=1\n";
+/// assert_eq!(pls::parse_bytes_with(body, &ParseOptions::default().max_bytes(16)), Err(ParseError::TooLarge(16)));
+/// ```
+pub fn parse_bytes_with(what: &[u8], opts: &ParseOptions) -> Result<Vec<PlaylistElement>, ParseError> {
+    if let Some(max) = opts.max_bytes.filter(|&max| what.len() > max) {
+        return Err(ParseError::TooLarge(max));
     }
-}
 
-impl From<ParseIntError> for ParseError {
-    fn from(e: ParseIntError) -> ParseError {
-        ParseError::InvalidInteger(e)
+    // Transcoding can make it longer, and it's been checked already
+    let transcoded = ParseOptions { max_bytes: None, ..opts.clone() };
+    if what.starts_with(b"\xFF\xFE") {
+        parse_str_with(&decode_utf16_lossy(&what[2..], u16::from_le_bytes), &transcoded)
+    } else if what.starts_with(b"\xFE\xFF") {
+        parse_str_with(&decode_utf16_lossy(&what[2..], u16::from_be_bytes), &transcoded)
+    } else {
+        parse_with(&mut &what[..], opts)
     }
 }
 
-impl ErrorT for ParseError {
-    fn description(&self) -> &str {
-        match *self {
-            ParseError::InvalidVersion(_) => "invalid version specified",
-            ParseError::MissingPlaylistSection => "[playlist] section missing",
-            ParseError::MissingKey(_) => "required key missing",
-            ParseError::InvalidInteger(ref e) => e.description(),
-            ParseError::Ini(ref e) => e.description(),
-        }
-    }
+/// Get the stream URL out of an internet radio playlist, which usually has only the one entry
+///
+/// That is, parse the data like `parse_bytes()`, and return the first element's path if it's a URL (per `PlaylistElement::is_url()`),
+/// or `None` if it isn't, or there are no elements.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(pls::first_url(b"[playlist]\n\
+///                              File1=http://127.0.0.1:8002/stream\n\
+///                              Title1=(#1 - 1/420) Radio\n\
+///                              NumberOfEntries=1\n").unwrap(),
+///            Some("http://127.0.0.1:8002/stream".to_string()));
+/// assert_eq!(pls::first_url(b"[playlist]\n\
+///                              File1=Track 1.mp3\n\
+///                              NumberOfEntries=1\n").unwrap(),
+///            None);
+/// assert!(pls::first_url(b"File1=http://127.0.0.1:8002/stream\n").is_err());
+/// ```
+pub fn first_url(data: &[u8]) -> Result<Option<String>, ParseError> {
+    first_url_with(data, &ParseOptions::default())
+}
 
-    fn cause(&self) -> Option<&ErrorT> {
-        match *self {
-            ParseError::InvalidInteger(ref e) => Some(e),
-            ParseError::Ini(ref e) => Some(e),
-            _ => None,
-        }
-    }
+/// Get the stream URL out of an internet radio playlist, like `first_url()`, but parsing it like `parse_bytes_with()`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let data = b"[playlist]\n\
+///              File1=http://127.0.0.1:8002/stream\n";
+/// assert!(pls::first_url(data).is_err());
+/// assert_eq!(pls::first_url_with(data, &ParseOptions::default().require_number_of_entries(false)).unwrap(),
+///            Some("http://127.0.0.1:8002/stream".to_string()));
+/// ```
+pub fn first_url_with(data: &[u8], opts: &ParseOptions) -> Result<Option<String>, ParseError> {
+    Ok(parse_bytes_with(data, opts)?.into_iter().next().filter(|e| e.is_url()).map(|e| e.path))
 }
 
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            ParseError::InvalidVersion(v) => write!(f, "Invalid version {} specified", v),
-            ParseError::MissingPlaylistSection => write!(f, "Missing [playlist] section"),
-            ParseError::MissingKey(ref k) => write!(f, "Key \"{}\" missing", k),
-            ParseError::InvalidInteger(ref e) => e.fmt(f),
-            ParseError::Ini(ref e) => e.fmt(f),
-        }
-    }
+/// Parse a playlist in the specified encoding, like Windows-1252 for ones written by old Winamp
+///
+/// A BOM, if any, overrides the encoding; malformed sequences are replaced with U+FFFD.
+///
+/// Requires the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate encoding_rs;
+/// # extern crate pls;
+/// # use pls::{PlaylistElement, ElementLength};
+/// # fn main() {
+/// assert_eq!(pls::parse_with_encoding(&mut &b"[playlist]\n\
+///                                             File1=Caf\xE9.mp3\n\
+///                                             NumberOfEntries=1\n"[..],
+///                                     encoding_rs::WINDOWS_1252).unwrap(),
+///            vec![PlaylistElement {
+///                path: "Café.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            }]);
+/// # }
+/// ```
+#[cfg(feature = "encoding")]
+pub fn parse_with_encoding<R: Read>(what: &mut R, encoding: &'static Encoding) -> Result<Vec<PlaylistElement>, ParseError> {
+    parse_with_encoding_and_options(what, encoding, &ParseOptions::default())
 }
 
-impl Clone for ParseError {
-    fn clone(&self) -> ParseError {
-        match *self {
-            ParseError::InvalidVersion(v) => ParseError::InvalidVersion(v),
-            ParseError::MissingPlaylistSection => ParseError::MissingPlaylistSection,
-            ParseError::MissingKey(ref k) => ParseError::MissingKey(k.clone()),
-            ParseError::InvalidInteger(ref e) => ParseError::InvalidInteger(e.clone()),
-            ParseError::Ini(ref e) => ParseError::Ini(ini::Error { msg: e.msg.clone(), ..*e }),
-        }
-    }
+/// Parse a playlist in the specified encoding, like `parse_with_encoding()`, configured by the specified options
+///
+/// `ParseOptions::max_bytes` limits the length of the input as-is, before it's decoded.
+///
+/// Requires the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate encoding_rs;
+/// # extern crate pls;
+/// # use pls::ParseOptions;
+/// # fn main() {
+/// let elems = pls::parse_with_encoding_and_options(&mut &b"[playlist]\n\
+///                                                          File1=Caf\xE9.mp3\n"[..],
+///                                                  encoding_rs::WINDOWS_1252,
+///                                                  &ParseOptions::default().require_number_of_entries(false))
+///     .unwrap();
+/// assert_eq!(pls::paths(&elems), ["Café.mp3"]);
+/// # }
+/// ```
+#[cfg(feature = "encoding")]
+pub fn parse_with_encoding_and_options<R: Read>(what: &mut R, encoding: &'static Encoding, opts: &ParseOptions) -> Result<Vec<PlaylistElement>, ParseError> {
+    let data = read_input(what, opts)?;
+    // Decoding can make it longer, and it's been checked already
+    parse_str_with(&encoding.decode(&data).0, &ParseOptions { max_bytes: None, ..opts.clone() })
 }
 
-impl PartialEq for ParseError {
-    fn eq(&self, rhs: &ParseError) -> bool {
-        match (self, rhs) {
-            (&ParseError::InvalidVersion(v), &ParseError::InvalidVersion(rv)) => v == rv,
-            (&ParseError::MissingPlaylistSection, &ParseError::MissingPlaylistSection) => true,
-            (&ParseError::MissingKey(ref k), &ParseError::MissingKey(ref rk)) => k == rk,
-            (&ParseError::InvalidInteger(ref e), &ParseError::InvalidInteger(ref re)) => e == re,
-            (&ParseError::Ini(ref e), &ParseError::Ini(ref re)) => e.line == re.line && e.col == re.col && e.msg == re.msg,
-            (_, _) => false,
+/// Parse several playlists, one after another, into one
+///
+/// The elements are concatenated in reader order; they'll be renumbered consecutively by `write()`.
+///
+/// On failure, returns the index of the reader that failed alongside the error.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, ParseError};
+/// assert_eq!(pls::parse_concat(vec![&b"[playlist]\n\
+///                                      File1=Track 1.mp3\n\
+///                                      NumberOfEntries=1\n"[..],
+///                                   &b"[playlist]\n\
+///                                      File1=Track 2.mp3\n\
+///                                      Length1=420\n\
+///                                      NumberOfEntries=1\n"[..]]).unwrap(),
+///            vec![PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            },
+///            PlaylistElement {
+///                path: "Track 2.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            }]);
+///
+/// assert_eq!(pls::parse_concat(vec![&b"[playlist]\n\
+///                                      NumberOfEntries=0\n"[..],
+///                                   &b"[playlist]\n"[..]]),
+///            Err((1, ParseError::MissingKey {
+///                key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+///                entry: None,
+///            })));
+/// ```
+pub fn parse_concat<R: Read, I: IntoIterator<Item = R>>(readers: I) -> Result<Vec<PlaylistElement>, (usize, ParseError)> {
+    parse_concat_with(readers, &ParseOptions::default())
+}
+
+/// Parse several playlists, one after another, into one, like `parse_concat()`, each configured by the specified options
+///
+/// So `ParseOptions::max_bytes` limits each playlist on its own, not all of them together,
+/// and `coalesce_adjacent_duplicates` doesn't collapse the last element of one with the first of the next.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let elems = pls::parse_concat_with(vec![&b"[playlist]\n\
+///                                            File1=Track 1.mp3\n"[..],
+///                                         &b"[playlist]\n\
+///                                            File1=Track 2.mp3\n"[..]],
+///                                    &ParseOptions::default().require_number_of_entries(false))
+///     .unwrap();
+/// assert_eq!(pls::paths(&elems), ["Track 1.mp3", "Track 2.mp3"]);
+/// ```
+pub fn parse_concat_with<R: Read, I: IntoIterator<Item = R>>(readers: I, opts: &ParseOptions) -> Result<Vec<PlaylistElement>, (usize, ParseError)> {
+    let mut elems = Vec::new();
+    for (i, mut r) in readers.into_iter().enumerate() {
+        elems.extend(parse_with(&mut r, opts).map_err(|e| (i, e))?);
+    }
+    Ok(elems)
+}
+
+/// Parse a file packing several playlists, each in its own `[playlist]` section, into one list of elements per section
+///
+/// Each `[playlist]` header (matched case-insensitively) starts a new playlist, read like `parse()` would read it on its own,
+/// up until the next `[playlist]` header, so its `NumberOfEntries`, `Version`, and `File#` indices only apply to that section,
+/// and each one's entries are numbered from 1.
+/// Keys before the first header are ignored, as are other sections.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let playlists = pls::parse_multi(&mut &b"[playlist]\n\
+///                                          File1=Track 1.mp3\n\
+///                                          NumberOfEntries=1\n\
+///                                          \n\
+///                                          [playlist]\n\
+///                                          File1=Track 2.mp3\n\
+///                                          File2=Track 3.mp3\n\
+///                                          NumberOfEntries=2\n"[..])
+///     .unwrap();
+/// assert_eq!(playlists.iter().map(|p| pls::paths(p)).collect::<Vec<_>>(),
+///            [vec!["Track 1.mp3"], vec!["Track 2.mp3", "Track 3.mp3"]]);
+/// ```
+pub fn parse_multi<R: Read>(what: &mut R) -> Result<Vec<Vec<PlaylistElement>>, ParseError> {
+    parse_multi_with(what, &ParseOptions::default())
+}
+
+/// Parse a file packing several playlists, like `parse_multi()`, each configured by the specified options
+///
+/// The sections are the ones named by `ParseOptions::section_name`, and `max_bytes` limits the whole file,
+/// while all other options apply to each section on its own.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let playlists = pls::parse_multi_with(&mut &b"[Radio]\n\
+///                                               File1=http://127.0.0.1:8002/stream\n\
+///                                               NumberOfEntries=1\n\
+///                                               [Radio]\n\
+///                                               File1=http://127.0.0.1:8003/stream\n\
+///                                               NumberOfEntries=1\n"[..],
+///                                       &ParseOptions::default().section_name("Radio"))
+///     .unwrap();
+/// assert_eq!(playlists.iter().map(|p| pls::paths(p)).collect::<Vec<_>>(),
+///            [vec!["http://127.0.0.1:8002/stream"], vec!["http://127.0.0.1:8003/stream"]]);
+/// ```
+pub fn parse_multi_with<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<Vec<Vec<PlaylistElement>>, ParseError> {
+    let data = read_input(what, opts)?;
+    let name = section_name(opts);
+
+    // Like parse(), skip the BOM, so that it doesn't hide the first header
+    let mut starts = vec![];
+    let mut pos = bom_len(&data);
+    for line in data[pos..].split_inclusive(|&b| b == b'\n') {
+        if str::from_utf8(line).ok().and_then(section_header).map(|s| s.eq_ignore_ascii_case(name)).unwrap_or(false) {
+            starts.push(pos);
+        }
+        pos += line.len();
+    }
+    if starts.is_empty() {
+        // Let parse_with() report what's wrong, or find a header too odd for the above
+        return parse_with(&mut &data[..], opts).map(|elems| vec![elems]);
+    }
+
+    starts.push(data.len());
+    starts.windows(2)
+        .map(|w| {
+            parse_with(&mut &data[w[0]..w[1]], opts).map_err(|e| match e {
+                ParseError::Utf8(at) => ParseError::Utf8(w[0] + at),
+                e => e,
+            })
+        })
+        .collect()
+}
+
+/// Parse the playlist at the start of `data`, followed by unrelated data, like in a container format,
+/// also returning how many bytes the playlist took
+///
+/// The playlist is read line by line, stopping at the first line that isn't blank, a `;` or `#` comment,
+/// the `[playlist]` header (which has to come before all keys), or a key `parse_full()` reads from it
+/// (`File#`, `Title#`, `Length#`, `Genre#`, an entry count key, `Version`, `PlaylistName`, or `Title`),
+/// like a line that isn't valid UTF-8 or the header of the next section.
+/// The playlist ends after the last header or key line before that, including its line ending;
+/// blank and comment lines after it are left to the following data.
+/// A leading UTF-8 BOM is skipped, like by `parse()`, and counted in the length.
+///
+/// Data that itself starts with recognised keys is indistinguishable from the end of the playlist, and will be parsed as part of it.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let data = b"[playlist]\n\
+///              File1=Track 1.mp3\n\
+///              NumberOfEntries=1\n\
+///              Version=2\n\
+///              \n\
+///              \x89PNG\r\n\x1a\n...";
+/// let (elems, len) = pls::parse_prefix(data).unwrap();
+/// assert_eq!(elems,
+///            vec![PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            }]);
+/// assert_eq!(&data[len..], b"\n\x89PNG\r\n\x1a\n...");
+/// ```
+pub fn parse_prefix(data: &[u8]) -> Result<(Vec<PlaylistElement>, usize), ParseError> {
+    parse_prefix_with(data, &ParseOptions::default())
+}
+
+/// Parse the playlist at the start of `data`, like `parse_prefix()`, configured by the specified options
+///
+/// The playlist starts with the header of the section named by `ParseOptions::section_name`,
+/// and `max_bytes` limits the length of the playlist, not of all of `data`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// let data = b"[playlist]\n\
+///              File1=Track 1.mp3\n\
+///              File3=Track 3.mp3\n\
+///              NumberOfEntries=3\n\
+///              [Other]\n";
+/// let (elems, len) = pls::parse_prefix_with(data, &ParseOptions::default().require_contiguous_indices(false)).unwrap();
+/// assert_eq!(pls::paths(&elems), ["Track 1.mp3", "Track 3.mp3"]);
+/// assert_eq!(&data[len..], b"[Other]\n");
+/// ```
+pub fn parse_prefix_with(data: &[u8], opts: &ParseOptions) -> Result<(Vec<PlaylistElement>, usize), ParseError> {
+    let name = section_name(opts);
+
+    // A BOM is part of the playlist, counted in its length
+    let mut len = 0;
+    let mut pos = bom_len(data);
+    let mut in_section = false;
+    for line in data[pos..].split_inclusive(|&b| b == b'\n') {
+        let content = match str::from_utf8(line) {
+            Ok(line) => line.trim(),
+            Err(_) => break,
+        };
+        pos += line.len();
+
+        if content.is_empty() || content.starts_with(';') || content.starts_with('#') {
+            continue;
+        }
+        if in_section {
+            let key = match content.find(&['=', ':'][..]) {
+                Some(i) => content[..i].trim_end(),
+                None => break,
+            };
+            if !is_playlist_key(key) {
+                break;
+            }
+        } else if section_header(content).map(|s| s.eq_ignore_ascii_case(name)).unwrap_or(false) {
+            in_section = true;
+        } else {
+            break;
+        }
+        len = pos;
+    }
+
+    if !in_section {
+        return Err(if data.iter().all(u8::is_ascii_whitespace) {
+            ParseError::Empty
+        } else {
+            ParseError::MissingPlaylistSection
+        });
+    }
+    Ok((parse_with(&mut &data[..len], opts)?, len))
+}
+
+/// Check whether the data is a structurally valid playlist
+///
+/// That is, whether it `parse()`s, and has exactly the `File#` keys from 1 through the entry count, no more.
+///
+/// # Examples
+///
+/// ```
+/// assert!(pls::is_valid_pls(b"[playlist]\n\
+///                             File1=Track 1.mp3\n\
+///                             NumberOfEntries=1\n"));
+/// assert!(!pls::is_valid_pls(b"[playlist]\n\
+///                              File1=Track 1.mp3\n\
+///                              File2=Track 2.mp3\n\
+///                              NumberOfEntries=1\n"));
+/// assert!(!pls::is_valid_pls(b"File1=Track 1.mp3\n"));
+/// ```
+pub fn is_valid_pls(data: &[u8]) -> bool {
+    let mut iter = match ParseIter::new(&mut &data[..], &ParseOptions::default()) {
+        Ok(iter) => iter,
+        Err(_) => return false,
+    };
+
+    // Sorted and deduplicated, so this length and maximum mean it's exactly 1..=count
+    let indices = present_indices(&iter.section, &["File"]);
+    indices.len() as u64 == iter.count && indices.last().map(|&i| i == iter.count).unwrap_or(true) && iter.all(|e| e.is_ok())
+}
+
+/// Lazy iterator over a playlist's elements, returned by `parse_iter()` and `parse_iter_with()`
+#[derive(Debug, Clone)]
+pub struct ParseIter {
+    section: ini::Properties,
+    opts: ParseOptions,
+    done: u64,
+    count: u64,
+    /// The `File#` indices to visit, in order, if not just `1..=count`
+    indices: Option<Vec<u64>>,
+    /// The entry count specified in the file, if any
+    declared: Option<u64>,
+    // Reused for every key lookup, so there's no allocation per key per entry
+    key: String,
+}
+
+impl ParseIter {
+    /// Get the indices from 1 through the entry count specified in the file that have no entry, in ascending order
+    ///
+    /// Without an entry count, which is only accepted without `ParseOptions::require_number_of_entries`,
+    /// this goes up to the highest index with an entry instead.
+    ///
+    /// This is only ever non-empty without `ParseOptions::require_contiguous_indices`,
+    /// since otherwise the first missing entry is an error.
+    ///
+    /// The entry count is just a number in the file, so a broken (or hostile) one, like `NumberOfEntries=18446744073709551615`,
+    /// makes nearly every index missing: `take()` as many as needed, instead of collecting them all.
+    /// Each one's found in constant time, by walking the gaps between entries, never by trying every index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::ParseOptions;
+    /// let data = b"[playlist]\n\
+    ///              File1=Track 1.mp3\n\
+    ///              File2=Track 2.mp3\n\
+    ///              File4=Track 4.mp3\n\
+    ///              NumberOfEntries=5\n";
+    ///
+    /// let iter = pls::parse_iter_with(&mut &data[..], &ParseOptions { require_contiguous_indices: false, ..ParseOptions::default() })
+    ///     .unwrap();
+    /// assert_eq!(iter.missing_indices().collect::<Vec<_>>(), vec![3, 5]);
+    /// assert_eq!(iter.len(), 3);
+    ///
+    /// let iter = pls::parse_iter(&mut &data[..]).unwrap();
+    /// assert_eq!(iter.missing_indices().count(), 0);
+    /// assert_eq!(iter.len(), 5);
+    /// ```
+    pub fn missing_indices(&self) -> impl Iterator<Item = u64> + '_ {
+        let present = self.indices.as_ref().map_or(&[][..], |i| &i[..]);
+        let bound = match self.indices {
+            Some(_) => self.declared.unwrap_or_else(|| present.last().cloned().unwrap_or(0)),
+            None => 0,
+        };
+
+        // Each entry ends the gap before it (present_indices() never yields 0), and the bound ends the last one;
+        // there's no gap after u64::MAX, so the next one starts at None there
+        let mut next = Some(1u64);
+        present.iter()
+            .map(|&i| (i - 1, i.checked_add(1)))
+            .chain(iter::once((bound, None)))
+            .flat_map(move |(last, after)| {
+                let gap = next.map(|first| first..=cmp::min(last, bound));
+                next = after;
+                gap.into_iter().flatten()
+            })
+    }
+
+    fn new<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<ParseIter, ParseError> {
+        let section_name = section_name(opts);
+
+        // Read it ourselves, so that I/O errors don't masquerade as located ini::Errors
+        let data = read_input(what, opts)?;
+        let len = data.len();
+        let mut data = &data[bom_len(&data)..];
+        if data.iter().all(u8::is_ascii_whitespace) {
+            return Err(ParseError::Empty);
+        }
+        if opts.skip_to_section {
+            let header = format!("[{}]", section_name);
+            if let Some(i) = data.windows(header.len()).position(|w| w.eq_ignore_ascii_case(header.as_bytes())) {
+                data = &data[i..];
+            }
+        }
+        let mut text = Cow::Borrowed(str::from_utf8(data).map_err(|e| ParseError::Utf8(len - data.len() + e.valid_up_to()))?);
+        if opts.join_continuation_lines && text.contains('\\') {
+            text = Cow::Owned(text.replace("\\\r\n", "").replace("\\\n", ""));
+        }
+
+        // Backslashes are common in Windows paths, and writers don't escape them
+        let mut p = ini::Ini::load_from_str_noescape(&text)?;
+        let preamble = if opts.allow_keys_before_section {
+            p.delete(None::<String>)
+        } else {
+            None
+        };
+        let mut play = take_section(p, section_name).ok_or(ParseError::MissingPlaylistSection)?;
+        for (k, v) in preamble.into_iter().flatten() {
+            play.entry(k).or_insert(v);
+        }
+        if opts.allow_ref_keys {
+            // Earlier prefixes win, since existing keys are never overwritten
+            for prefix in &["Ref", "HREF"] {
+                let aliased: Vec<_> = play.iter()
+                    .filter_map(|(k, v)| k.strip_prefix(prefix).map(|idx| (idx, v)))
+                    .filter(|&(idx, _)| !idx.is_empty() && !idx.starts_with('0') && idx.bytes().all(|b| b.is_ascii_digit()))
+                    .map(|(idx, v)| (format!("File{}", idx), v.clone()))
+                    .collect();
+                for (k, v) in aliased {
+                    play.entry(k).or_insert(v);
+                }
+            }
+        }
+        for v in play.values_mut() {
+            if v.ends_with('\0') {
+                let len = v.trim_end_matches('\0').trim_end().len();
+                v.truncate(len);
+            }
+        }
+
+        match play.get("Version") {
+            Some(v) => {
+                let v = parse_integer("Version", v)?;
+                if opts.allowed_versions.as_ref().map(|vs| !vs.contains(&v)).unwrap_or(false) {
+                    return Err(ParseError::InvalidVersion(v));
+                }
+            }
+            None if opts.require_version => {
+                return Err(ParseError::MissingKey {
+                    key: "Version".to_string(),
+                    entry: None,
+                })
+            }
+            None => {}
+        }
+
+        // Some major radio stations have malformed pls files, handle without error:
+        // "numberofentries" http://newmedia.kcrw.com/legacy/pls/kcrwsimulcast.pls
+        // "NumberOfEvents" http://www.abc.net.au/res/streaming/audio/mp3/classic_fm.pls
+        // so accept both in any capitalisation, preferring the canonical one, then the others of NumberOfEntries
+        let mut counts: Vec<_> = play.iter().filter(|&(k, _)| is_count_key(k)).collect();
+        counts.sort_unstable_by_key(|&(k, _)| (!k.eq_ignore_ascii_case("NumberOfEntries"), k != "NumberOfEntries", k));
+        let mut counts = counts.into_iter();
+        let count = match counts.next() {
+            Some((k, e)) => Some(parse_integer(k, e)?),
+            None if opts.require_number_of_entries => {
+                return Err(ParseError::MissingKey {
+                    key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+                    entry: None,
+                })
+            }
+            None => None,
+        };
+        if opts.reject_conflicting_counts {
+            for (k, e) in counts {
+                if Some(parse_integer::<u64>(k, e)?) != count {
+                    return Err(ParseError::ConflictingCountKeys);
+                }
+            }
+        }
+
+        let declared = count;
+        let (count, indices) = match count {
+            Some(count) if opts.require_contiguous_indices => (count, None),
+            _ => {
+                let prefixes: &[&str] = if opts.allow_missing_file {
+                    &["File", "Title", "Length", "Genre"]
+                } else {
+                    &["File"]
+                };
+                let indices = present_indices(&play, prefixes);
+                (indices.len() as u64, Some(indices))
+            }
+        };
+
+        Ok(ParseIter {
+            section: play,
+            opts: opts.clone(),
+            done: 0,
+            count,
+            indices,
+            declared,
+            key: String::new(),
+        })
+    }
+
+    fn entry(&mut self, i: &EntryIndex) -> Result<PlaylistElement, ParseError> {
+        // The entries are only visited once, so move the values out instead of cloning them
+        let path = self.section.remove(i.key(&mut self.key, "File"));
+        let title = self.section.remove(i.key(&mut self.key, "Title"));
+        let len = self.section.remove(i.key(&mut self.key, "Length"));
+        let genre = self.section.remove(i.key(&mut self.key, "Genre"));
+
+        let path = match path {
+            Some(path) => path,
+            None if self.opts.allow_missing_file && (title.is_some() || len.is_some()) => String::new(),
+            None => {
+                return Err(ParseError::MissingKey {
+                    key: i.key(&mut self.key, "File").to_string(),
+                    entry: Some(i.value),
+                })
+            }
+        };
+        let len = match len {
+            Some(_) if self.opts.ignore_live_stream_lengths && is_live_url(&path) => ElementLength::Unknown,
+            Some(len) => {
+                let value = if self.opts.allow_length_units { strip_length_unit(&len) } else { &len[..] };
+                let negative = value.strip_prefix('-').map(|d| !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit())).unwrap_or(false);
+                let parsed = match round_fractional(value) {
+                    _ if negative && self.opts.negative_length_is_unknown => Ok(ElementLength::Unknown),
+                    Some(secs) if self.opts.round_fractional_lengths => secs.map(ElementLength::Seconds),
+                    _ => {
+                        match clock_length(value) {
+                            Some(secs) if self.opts.allow_clock_lengths => secs.map(ElementLength::Seconds),
+                            _ => value.parse::<ElementLength>(),
+                        }
+                    }
+                };
+                match parsed {
+                    Ok(len) => len,
+                    Err(ref e) if *e.kind() == IntErrorKind::PosOverflow && self.opts.clamp_overflowing_lengths => ElementLength::Seconds(u64::MAX),
+                    Err(ref e) if *e.kind() == IntErrorKind::PosOverflow => {
+                        return Err(ParseError::LengthOverflow {
+                            key: i.key(&mut self.key, "Length").to_string(),
+                            value: len,
+                        })
+                    }
+                    Err(e) => {
+                        return Err(ParseError::InvalidInteger {
+                            key: i.key(&mut self.key, "Length").to_string(),
+                            value: len,
+                            source: e,
+                        })
+                    }
+                }
+            }
+            None => ElementLength::Unknown,
+        };
+        Ok(PlaylistElement {
+            path,
+            title,
+            len,
+            genre,
+        })
+    }
+
+    /// Parse all remaining entries, for `parse_with()` and friends
+    fn collect_elements(self) -> Result<Vec<PlaylistElement>, ParseError> {
+        let coalesce = self.opts.coalesce_adjacent_duplicates;
+
+        // NumberOfEntries is untrusted, but there can't be more entries than keys
+        let mut elems: Vec<PlaylistElement> = Vec::with_capacity(cmp::min(self.len(), self.section.len()));
+        for elem in self {
+            let elem = elem?;
+            if coalesce && elems.last().map(|l| l.path == elem.path).unwrap_or(false) {
+                continue;
+            }
+            elems.push(elem);
+        }
+        Ok(elems)
+    }
+
+    /// Parse the next entry, along with the `File#` index it was read from
+    fn next_indexed(&mut self) -> Option<Result<(u64, PlaylistElement), ParseError>> {
+        if self.done == self.count {
+            return None;
+        }
+
+        let i = EntryIndex::new(match self.indices {
+            Some(ref indices) => indices[self.done as usize],
+            None => self.done + 1,
+        });
+        self.done += 1;
+        Some(self.entry(&i).map(|e| (i.value, e)))
+    }
+}
+
+impl Iterator for ParseIter {
+    type Item = Result<PlaylistElement, ParseError>;
+
+    fn next(&mut self) -> Option<Result<PlaylistElement, ParseError>> {
+        self.next_indexed().map(|r| r.map(|(_, e)| e))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = cmp::min(self.count - self.done, usize::MAX as u64) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for ParseIter {}
+
+
+/// Write a playlist to the specified output stream
+///
+/// Only what's in the elements is written, so values are normalised:
+/// a parsed `Length1=007` is `Seconds(7)`, and is written as `Length1=7`.
+/// Use `Document` to keep the original text.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let mut buf = Vec::new();
+/// pls::write(&[PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: Some("Unknown Artist - Track 1".to_string()),
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            },
+///            PlaylistElement {
+///                path: "Track 2.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            },
+///            PlaylistElement {
+///                path: "Track 3.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            }],
+///            &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             Title1=Unknown Artist - Track 1\n\
+///             \n\
+///             File2=Track 2.mp3\n\
+///             Length2=420\n\
+///             \n\
+///             File3=Track 3.mp3\n\
+///             \n\
+///             NumberOfEntries=3\n\
+///             Version=2\n")
+/// ```
+pub fn write<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, to: &mut W) -> io::Result<()> {
+    write_with(what, &WriteOptions::default(), to)
+}
+
+/// Write a playlist to the specified output stream, configured by the specified options
+///
+/// `write()` is this with the default options, and the other convenience functions delegate to it.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// # use std::io;
+/// let mut buf = Vec::new();
+/// pls::write_with(&[PlaylistElement {
+///                     path: "Track 1.mp3".to_string(),
+///                     title: Some("Unknown Artist - Track 1".to_string()),
+///                     len: ElementLength::Seconds(420),
+///                     genre: None,
+///                 }],
+///                 &WriteOptions { align_keys: true, ..WriteOptions::default() },
+///                 &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1  =Track 1.mp3\n\
+///             Title1 =Unknown Artist - Track 1\n\
+///             Length1=420\n\
+///             \n\
+///             NumberOfEntries=1\n\
+///             Version=2\n");
+///
+/// let mut buf = Vec::new();
+/// assert_eq!(pls::write_with(&[] as &[PlaylistElement], &WriteOptions { error_on_empty: true, ..WriteOptions::default() }, &mut buf)
+///                .unwrap_err()
+///                .kind(),
+///            io::ErrorKind::InvalidInput);
+/// assert!(buf.is_empty());
+/// ```
+pub fn write_with<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, opts: &WriteOptions, to: &mut W)
+                                                                                                         -> io::Result<()> {
+    write_named(None, what, opts, to)
+}
+
+/// Write a playlist into a new buffer, like `write()`, for when it goes straight into something like an HTTP response body
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(pls::to_bytes(&[PlaylistElement {
+///                              path: "Track 1.mp3".to_string(),
+///                              title: None,
+///                              len: ElementLength::Seconds(420),
+///                              genre: None,
+///                          }]),
+///            b"[playlist]\n\
+///              File1=Track 1.mp3\n\
+///              Length1=420\n\
+///              \n\
+///              NumberOfEntries=1\n\
+///              Version=2\n");
+/// ```
+pub fn to_bytes<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>>(what: I) -> Vec<u8> {
+    to_bytes_with(what, &WriteOptions::default())
+}
+
+/// Write a playlist into a new buffer, like `write_with()`
+///
+/// With `error_on_empty`, the buffer is empty if there are no elements.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, WriteOptions};
+/// assert!(pls::to_bytes_with(&[] as &[PlaylistElement], &WriteOptions::default().error_on_empty(true)).is_empty());
+/// ```
+pub fn to_bytes_with<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>>(what: I, opts: &WriteOptions) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // Writing to a Vec can't fail, and error_on_empty fails before writing anything
+    let _ = write_with(what, opts, &mut buf);
+    buf
+}
+
+/// Write a playlist like `write()`, returning the amount of bytes written
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let mut buf = Vec::new();
+/// let written = pls::write_counted(&[PlaylistElement {
+///                                      path: "Track 1.mp3".to_string(),
+///                                      title: None,
+///                                      len: ElementLength::Unknown,
+///                                      genre: None,
+///                                  }],
+///                                  &mut buf)
+///     .unwrap();
+/// assert_eq!(written, buf.len());
+/// assert_eq!(written, 58);
+/// ```
+pub fn write_counted<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, to: &mut W) -> io::Result<usize> {
+    write_counted_with(what, &WriteOptions::default(), to)
+}
+
+/// Write a playlist like `write_with()`, returning the amount of bytes written
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, LineEnding, WriteOptions};
+/// let written = pls::write_counted_with(&[PlaylistElement {
+///                                           path: "Track 1.mp3".to_string(),
+///                                           title: None,
+///                                           len: ElementLength::Unknown,
+///                                           genre: None,
+///                                       }],
+///                                       &WriteOptions::default().line_ending(LineEnding::CrLf),
+///                                       &mut Vec::new())
+///     .unwrap();
+/// assert_eq!(written, 58 + 5);
+/// ```
+pub fn write_counted_with<'i, S, I, W>(what: I, opts: &WriteOptions, to: &mut W) -> io::Result<usize>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          W: Write
+{
+    let mut counted = CountingWriter {
+        inner: to,
+        count: 0,
+    };
+    write_with(what, opts, &mut counted)?;
+    Ok(counted.count)
+}
+
+/// Check what `write_with()` would write, without writing anything, like for previewing an export
+///
+/// Fails like `write_with()` would, when `error_on_empty` is set and there are no elements.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions, WritePreview, WriteWarning};
+/// # let elem = |p: &str, t: &str| PlaylistElement { path: p.to_string(), title: Some(t.to_string()), len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3", "Unknown Artist - Track 1"), elem("", "Unknown Artist; Other Artist - Track 2")];
+/// assert_eq!(pls::validate_for_write(&elements, &WriteOptions::default()).unwrap(),
+///            WritePreview {
+///                bytes: pls::to_bytes(&elements).len(),
+///                entries: 2,
+///                warnings: vec![WriteWarning::EmptyPath { key: "File2".to_string() }, WriteWarning::Altered { key: "Title2".to_string() }],
+///            });
+/// ```
+pub fn validate_for_write<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>>(what: I, opts: &WriteOptions)
+                                                                                                      -> io::Result<WritePreview> {
+    let mut entries = 0u64;
+    let mut warnings = vec![];
+    let mut sink = io::sink();
+    let mut counted = CountingWriter {
+        inner: &mut sink,
+        count: 0,
+    };
+    write_with(what.into_iter().inspect(|elem| {
+                   value_warnings(opts.start_index.wrapping_add(entries), elem, &mut warnings);
+                   entries += 1;
+               }),
+               opts,
+               &mut counted)?;
+
+    Ok(WritePreview {
+        bytes: counted.count,
+        entries,
+        warnings,
+    })
+}
+
+/// Check that writing `elements` with `write()` and reading them back with `parse()` yields the same elements,
+/// panicking with the written playlist otherwise
+///
+/// This is for tests, of this crate and of ones building on it, and isn't part of the stable API.
+/// Elements `validate_for_write()` warns about don't round-trip.
+#[doc(hidden)]
+pub fn assert_round_trip(elements: &[PlaylistElement]) {
+    let written = to_bytes(elements);
+    match parse(&mut &written[..]) {
+        Ok(ref parsed) if parsed == elements => {}
+        parsed => panic!("{:?} read back as {:?}, from:\n{}", elements, parsed, String::from_utf8_lossy(&written)),
+    }
+}
+
+/// Write only the elements in the specified range of positions, as a playlist of its own, numbered from 1
+///
+/// Fails with `io::ErrorKind::InvalidInput`, without writing anything, if the range is out of bounds or decreasing.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3"), elem("Track 2.mp3"), elem("Track 3.mp3")];
+///
+/// let mut buf = Vec::new();
+/// pls::write_range(&elements, 1..3, &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 2.mp3\n\
+///             \n\
+///             File2=Track 3.mp3\n\
+///             \n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+///
+/// assert!(pls::write_range(&elements, 2..4, &mut Vec::new()).is_err());
+/// ```
+pub fn write_range<S: AsRef<str>, W: Write>(elements: &[PlaylistElementGen<S>], range: Range<usize>, to: &mut W) -> io::Result<()> {
+    write_range_with(elements, range, &WriteOptions::default(), to)
+}
+
+/// Write only the elements in the specified range of positions, like `write_range()`, configured by the specified options
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3"), elem("Track 2.mp3"), elem("Track 3.mp3")];
+///
+/// let mut buf = Vec::new();
+/// pls::write_range_with(&elements, 2..3, &WriteOptions::default().start_index(3), &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File3=Track 3.mp3\n\
+///             \n\
+///             NumberOfEntries=1\n\
+///             Version=2\n");
+/// ```
+pub fn write_range_with<S: AsRef<str>, W: Write>(elements: &[PlaylistElementGen<S>], range: Range<usize>, opts: &WriteOptions, to: &mut W)
+                                                 -> io::Result<()> {
+    match elements.get(range.clone()) {
+        Some(elements) => write_with(elements, opts, to),
+        None => {
+            Err(io::Error::new(io::ErrorKind::InvalidInput,
+                               format!("range {:?} out of bounds for a playlist of {} elements", range, elements.len())))
+        }
+    }
+}
+
+/// Write only the elements for which `keep` returns `true`, without collecting them first
+///
+/// The surviving elements are renumbered consecutively from 1, in their original order,
+/// and `NumberOfEntries` is how many of them there are.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str, t: &str| PlaylistElement { path: p.to_string(), title: Some(t.to_string()), len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3", "Unknown Artist - Track 1"),
+///                 elem("Track 2.mp3", "[hidden]"),
+///                 elem("Track 3.mp3", "Unknown Artist - Track 3")];
+///
+/// let mut buf = Vec::new();
+/// pls::write_filtered(&elements, |e| e.title_str() != "[hidden]", &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             Title1=Unknown Artist - Track 1\n\
+///             \n\
+///             File2=Track 3.mp3\n\
+///             Title2=Unknown Artist - Track 3\n\
+///             \n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+/// ```
+pub fn write_filtered<'i, S, I, F, W>(what: I, keep: F, to: &mut W) -> io::Result<()>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          F: Fn(&PlaylistElementGen<S>) -> bool,
+          W: Write
+{
+    write_filtered_with(what, keep, &WriteOptions::default(), to)
+}
+
+/// Write only the elements for which `keep` returns `true`, like `write_filtered()`, configured by the specified options
+///
+/// With `error_on_empty`, this fails if `keep` rejects all elements.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3"), elem("Track 2.mp3")];
+/// let opts = WriteOptions::default().error_on_empty(true);
+/// assert!(pls::write_filtered_with(&elements, |e| e.path == "Track 2.mp3", &opts, &mut Vec::new()).is_ok());
+/// assert!(pls::write_filtered_with(&elements, |_| false, &opts, &mut Vec::new()).is_err());
+/// ```
+pub fn write_filtered_with<'i, S, I, F, W>(what: I, keep: F, opts: &WriteOptions, to: &mut W) -> io::Result<()>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          F: Fn(&PlaylistElementGen<S>) -> bool,
+          W: Write
+{
+    write_with(what.into_iter().filter(|e| keep(e)), opts, to)
+}
+
+/// Write the elements of a map keyed by position, like from an editing model with stable IDs, in key order
+///
+/// The keys only order the elements, and can be sparse:
+/// the elements are renumbered consecutively from 1, so the one with the smallest key is `File1`, the next one `File2`, and so on.
+/// A `HashMap` has no order to keep, so collect it into a `BTreeMap` first.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::collections::BTreeMap;
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut entries = BTreeMap::new();
+/// entries.insert(30, elem("Track 3.mp3"));
+/// entries.insert(7, elem("Track 1.mp3"));
+/// entries.insert(12, elem("Track 2.mp3"));
+///
+/// let mut buf = Vec::new();
+/// pls::write_indexed(&entries, &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             \n\
+///             File2=Track 2.mp3\n\
+///             \n\
+///             File3=Track 3.mp3\n\
+///             \n\
+///             NumberOfEntries=3\n\
+///             Version=2\n");
+/// ```
+pub fn write_indexed<S: AsRef<str>, W: Write>(entries: &BTreeMap<usize, PlaylistElementGen<S>>, to: &mut W) -> io::Result<()> {
+    write_indexed_with(entries, &WriteOptions::default(), to)
+}
+
+/// Write the elements of a map keyed by position, like `write_indexed()`, configured by the specified options
+///
+/// The elements are still renumbered consecutively, from `start_index`, not by their keys.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// # use std::collections::BTreeMap;
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut entries = BTreeMap::new();
+/// entries.insert(12, elem("Track 2.mp3"));
+/// entries.insert(7, elem("Track 1.mp3"));
+///
+/// let mut buf = Vec::new();
+/// pls::write_indexed_with(&entries, &WriteOptions::default().start_index(0).blank_line_between_entries(false), &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File0=Track 1.mp3\n\
+///             File1=Track 2.mp3\n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+/// ```
+pub fn write_indexed_with<S: AsRef<str>, W: Write>(entries: &BTreeMap<usize, PlaylistElementGen<S>>, opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+    write_with(entries.values(), opts, to)
+}
+
+/// Write a playlist in canonical form, for formatting playlists kept in version control
+///
+/// The canonical form is what `write()` produces, after replacing every run of whitespace in titles and genres
+/// (including line breaks) with a single space, trimming them, and leaving out the ones that end up empty.
+/// Paths and lengths are written as-is, and the order of the elements is kept, since it's meaningful.
+///
+/// So the output is independent of the original formatting, and parsing and writing it again produces the same bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let mut buf = Vec::new();
+/// pls::write_canonical(&[PlaylistElement {
+///                          path: "Track 1.mp3".to_string(),
+///                          title: Some("  Unknown Artist -\r\n\tTrack 1 ".to_string()),
+///                          len: ElementLength::Seconds(420),
+///                          genre: Some(" ".to_string()),
+///                      }],
+///                      &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             Title1=Unknown Artist - Track 1\n\
+///             Length1=420\n\
+///             \n\
+///             NumberOfEntries=1\n\
+///             Version=2\n");
+/// ```
+pub fn write_canonical<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, to: &mut W) -> io::Result<()> {
+    write_canonical_with(what, &WriteOptions::default(), to)
+}
+
+/// Write a playlist in canonical form, like `write_canonical()`, but formatted by the specified options instead of like `write()`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, LineEnding, WriteOptions};
+/// let mut buf = Vec::new();
+/// pls::write_canonical_with(&[PlaylistElement {
+///                               path: "Track 1.mp3".to_string(),
+///                               title: Some("  Unknown Artist -\r\n\tTrack 1 ".to_string()),
+///                               len: ElementLength::Unknown,
+///                               genre: None,
+///                           }],
+///                           &WriteOptions::default().line_ending(LineEnding::CrLf),
+///                           &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\r\n\
+///             File1=Track 1.mp3\r\n\
+///             Title1=Unknown Artist - Track 1\r\n\
+///             \r\n\
+///             NumberOfEntries=1\r\n\
+///             Version=2\r\n");
+/// ```
+pub fn write_canonical_with<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, opts: &WriteOptions, to: &mut W)
+                                                                                                                   -> io::Result<()> {
+    let canonical: Vec<_> = what.into_iter()
+        .map(|e| {
+            PlaylistElementGen {
+                path: Cow::Borrowed(e.path.as_ref()),
+                title: e.title.as_ref().and_then(|t| canonical_text(t.as_ref())).map(Cow::Owned),
+                len: e.len,
+                genre: e.genre.as_ref().and_then(|g| canonical_text(g.as_ref())).map(Cow::Owned),
+            }
+        })
+        .collect();
+    write_with(&canonical, opts, to)
+}
+
+/// Write a playlist like `write()`, but in the specified encoding, like Windows-1252 for old Winamp
+///
+/// Characters the encoding can't represent are replaced with `?` if `lossy`,
+/// otherwise writing fails with `io::ErrorKind::InvalidData` before anything is written.
+///
+/// `encoding_rs` can't encode into UTF-16 (or the replacement encoding), so those produce UTF-8.
+///
+/// Requires the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate encoding_rs;
+/// # extern crate pls;
+/// # use pls::{PlaylistElement, ElementLength};
+/// # fn main() {
+/// let elements = [PlaylistElement {
+///                     path: "Café.mp3".to_string(),
+///                     title: Some("Unknown Artist — Café".to_string()),
+///                     len: ElementLength::Unknown,
+///                     genre: None,
+///                 }];
+///
+/// let mut buf = Vec::new();
+/// pls::write_with_encoding(&elements, encoding_rs::ISO_8859_2, true, &mut buf).unwrap();
+/// assert_eq!(buf,
+///            &b"[playlist]\n\
+///               File1=Caf\xE9.mp3\n\
+///               Title1=Unknown Artist ? Caf\xE9\n\
+///               \n\
+///               NumberOfEntries=1\n\
+///               Version=2\n"[..]);
+///
+/// assert!(pls::write_with_encoding(&elements, encoding_rs::ISO_8859_2, false, &mut Vec::new()).is_err());
+/// # }
+/// ```
+#[cfg(feature = "encoding")]
+pub fn write_with_encoding<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, encoding: &'static Encoding,
+                                                                                                                  lossy: bool, to: &mut W)
+                                                                                                                  -> io::Result<()> {
+    write_with_encoding_and_options(what, encoding, lossy, &WriteOptions::default(), to)
+}
+
+/// Write a playlist in the specified encoding, like `write_with_encoding()`, configured by the specified options
+///
+/// With `byte_order_mark`, the BOM is encoded like everything else, so it's only kept for UTF-8, and an encoding error otherwise if not `lossy`.
+///
+/// Requires the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate encoding_rs;
+/// # extern crate pls;
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// # fn main() {
+/// let mut buf = Vec::new();
+/// pls::write_with_encoding_and_options(&[PlaylistElement {
+///                                          path: "Café.mp3".to_string(),
+///                                          title: None,
+///                                          len: ElementLength::Unknown,
+///                                          genre: None,
+///                                      }],
+///                                      encoding_rs::WINDOWS_1252,
+///                                      false,
+///                                      &WriteOptions::default().emit_version(false),
+///                                      &mut buf)
+///     .unwrap();
+/// assert_eq!(buf,
+///            &b"[playlist]\n\
+///               File1=Caf\xE9.mp3\n\
+///               \n\
+///               NumberOfEntries=1\n"[..]);
+/// # }
+/// ```
+#[cfg(feature = "encoding")]
+pub fn write_with_encoding_and_options<'i, S, I, W>(what: I, encoding: &'static Encoding, lossy: bool, opts: &WriteOptions, to: &mut W) -> io::Result<()>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          W: Write
+{
+    let mut utf8 = Vec::new();
+    write_with(what, opts, &mut utf8)?;
+    // Everything written comes from Strings
+    let utf8 = String::from_utf8(utf8).unwrap();
+
+    let mut encoder = encoding.new_encoder();
+    let mut out = Vec::new();
+    let mut src = &utf8[..];
+    loop {
+        let (res, read) = encoder.encode_from_utf8_to_vec_without_replacement(src, &mut out, true);
+        src = &src[read..];
+        match res {
+            EncoderResult::InputEmpty => break,
+            EncoderResult::OutputFull => out.reserve(encoder.max_buffer_length_from_utf8_without_replacement(src.len()).unwrap_or(src.len())),
+            EncoderResult::Unmappable(_) if lossy => {
+                out.reserve(8);
+                let _ = encoder.encode_from_utf8_to_vec_without_replacement("?", &mut out, false);
+            }
+            EncoderResult::Unmappable(c) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("{:?} can't be represented in {}", c, encoding.output_encoding().name())))
+            }
+        }
+    }
+
+    to.write_all(&out)
+}
+
+/// Write the line opening a playlist, before all entries
+///
+/// This, `write_entry()`, and `write_footer()` are the building blocks of `write()`, for assembling a playlist by hand;
+/// their `_with()` variants are those of `write_with()`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::io::Write;
+/// let mut buf = Vec::new();
+/// pls::write_header(&mut buf).unwrap();
+/// writeln!(buf, "; Exported by Unknown Player").unwrap();
+/// pls::write_entry(1,
+///                  &PlaylistElement {
+///                      path: "Track 1.mp3".to_string(),
+///                      title: None,
+///                      len: ElementLength::Seconds(420),
+///                      genre: None,
+///                  },
+///                  &mut buf)
+///     .unwrap();
+/// pls::write_footer(1, &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             ; Exported by Unknown Player\n\
+///             File1=Track 1.mp3\n\
+///             Length1=420\n\
+///             \n\
+///             NumberOfEntries=1\n\
+///             Version=2\n")
+/// ```
+pub fn write_header<W: Write>(to: &mut W) -> io::Result<()> {
+    write_header_with(&WriteOptions::default(), to)
+}
+
+/// Write the line opening a playlist, like `write_header()`, configured by the specified options
+///
+/// This writes the byte order mark, with `byte_order_mark`, and ends the line with `line_ending`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{LineEnding, WriteOptions};
+/// let mut buf = Vec::new();
+/// pls::write_header_with(&WriteOptions::default().line_ending(LineEnding::CrLf), &mut buf).unwrap();
+/// assert_eq!(buf, b"[playlist]\r\n");
+/// ```
+pub fn write_header_with<W: Write>(opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+    if opts.byte_order_mark {
+        to.write_all("\u{FEFF}".as_bytes())?;
+    }
+    write!(to, "[playlist]{}", opts.line_ending.as_str())
+}
+
+/// Write a single entry, with the specified 1-based index, followed by a blank line
+///
+/// Entries need to be numbered consecutively from 1 for the playlist to be valid.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// let mut buf = Vec::new();
+/// pls::write_entry(3,
+///                  &PlaylistElement {
+///                      path: "Track 3.mp3".to_string(),
+///                      title: Some("Unknown Artist - Track 3".to_string()),
+///                      len: ElementLength::Unknown,
+///                      genre: Some("Electronic".to_string()),
+///                  },
+///                  &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "File3=Track 3.mp3\n\
+///             Title3=Unknown Artist - Track 3\n\
+///             Genre3=Electronic\n\
+///             \n")
+/// ```
+pub fn write_entry<S: AsRef<str>, W: Write>(idx: u64, what: &PlaylistElementGen<S>, to: &mut W) -> io::Result<()> {
+    write_entry_with(idx, what, &WriteOptions::default(), to)
+}
+
+/// Write a single entry, with the specified index, like `write_entry()`, configured by the specified options
+///
+/// `idx` is used as-is, ignoring `start_index`, and the blank line is only written with `blank_line_between_entries`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, LineEnding, WriteOptions};
+/// let mut buf = Vec::new();
+/// pls::write_entry_with(3,
+///                       &PlaylistElement {
+///                           path: "Track 3.mp3".to_string(),
+///                           title: None,
+///                           len: ElementLength::Seconds(420),
+///                           genre: None,
+///                       },
+///                       &WriteOptions::default().line_ending(LineEnding::CrLf).blank_line_between_entries(false),
+///                       &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "File3=Track 3.mp3\r\n\
+///             Length3=420\r\n")
+/// ```
+pub fn write_entry_with<S: AsRef<str>, W: Write>(idx: u64, what: &PlaylistElementGen<S>, opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+    write_entry_keys(idx, what, opts, to)?;
+    if opts.blank_line_between_entries {
+        to.write_all(opts.line_ending.as_str().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write the lines closing a playlist of `count` entries, after all entries
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = Vec::new();
+/// pls::write_footer(3, &mut buf).unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "NumberOfEntries=3\n\
+///             Version=2\n")
+/// ```
+pub fn write_footer<W: Write>(count: u64, to: &mut W) -> io::Result<()> {
+    write_footer_with(count, &WriteOptions::default(), to)
+}
+
+/// Write the lines closing a playlist of `count` entries, like `write_footer()`, configured by the specified options
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{LineEnding, WriteOptions};
+/// let mut buf = Vec::new();
+/// pls::write_footer_with(3, &WriteOptions::default().line_ending(LineEnding::CrLf).emit_version(false), &mut buf).unwrap();
+/// assert_eq!(buf, b"NumberOfEntries=3\r\n");
+/// ```
+pub fn write_footer_with<W: Write>(count: u64, opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+    let nl = opts.line_ending.as_str();
+    if opts.emit_number_of_entries {
+        write!(to, "NumberOfEntries={}{}", count, nl)?;
+    }
+    if opts.emit_version {
+        write!(to, "Version=2{}", nl)?;
+    }
+    Ok(())
+}
+
+/// Add elements to the end of the playlist at the specified path, creating it if it doesn't exist
+///
+/// The existing playlist is parsed and the whole file rewritten with all elements, renumbered, so it stays valid.
+/// It's replaced atomically, like by `write_file_atomic()`, so if anything fails, it's left as it was, with all of its old entries.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength};
+/// pls::append_file("Favourites.pls",
+///                  &[PlaylistElement {
+///                      path: "Track 4.mp3".to_string(),
+///                      title: None,
+///                      len: ElementLength::Unknown,
+///                      genre: None,
+///                  }])
+///     .unwrap();
+/// ```
+pub fn append_file<P: AsRef<Path>>(path: P, new: &[PlaylistElement]) -> Result<(), ParseError> {
+    append_file_with(path, new, &WriteOptions::default())
+}
+
+/// Add elements to the end of the playlist at the specified path, like `append_file()`, writing it with the specified options
+///
+/// The existing playlist is still parsed with `parse()`, so appending to it again fails if `opts` writes one `parse()` can't read,
+/// like with a `start_index` other than 1, or without `emit_number_of_entries`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength, LineEnding, WriteOptions};
+/// pls::append_file_with("Favourites.pls",
+///                       &[PlaylistElement {
+///                           path: "Track 4.mp3".to_string(),
+///                           title: None,
+///                           len: ElementLength::Unknown,
+///                           genre: None,
+///                       }],
+///                       &WriteOptions::default().line_ending(LineEnding::CrLf))
+///     .unwrap();
+/// ```
+pub fn append_file_with<P: AsRef<Path>>(path: P, new: &[PlaylistElement], opts: &WriteOptions) -> Result<(), ParseError> {
+    let path = path.as_ref();
+    let old = match File::open(path) {
+        Ok(mut f) => parse(&mut f)?,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e.into()),
+    };
+
+    replace_file(path, |out| write_with(old.iter().chain(new), opts, out))?;
+    Ok(())
+}
+
+/// Write a playlist to the file at the specified path, in the format its extension implies, replacing it if it exists
+///
+/// Supported extensions, matched case-insensitively:
+///
+///   * `.pls`: `write()`,
+///   * `.m3u` and `.m3u8`: `write_m3u()`.
+///
+/// Other extensions (or none at all), including those of formats this crate doesn't write, like `.xspf` or `.asx`,
+/// fail with `io::ErrorKind::InvalidInput`, before the file's touched.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength};
+/// let elements = [PlaylistElement {
+///                     path: "Track 1.mp3".to_string(),
+///                     title: None,
+///                     len: ElementLength::Unknown,
+///                     genre: None,
+///                 }];
+/// pls::write_to_path(&elements, "Favourites.pls").unwrap();
+/// pls::write_to_path(&elements, "Favourites.m3u8").unwrap();
+/// assert!(pls::write_to_path(&elements, "Favourites.xspf").is_err());
+/// ```
+pub fn write_to_path<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, P: AsRef<Path>>(what: I, path: P) -> io::Result<()> {
+    write_to_path_with(what, path, &WriteOptions::default())
+}
+
+/// Write a playlist to the file at the specified path, like `write_to_path()`, configured by the specified options
+///
+/// M3U playlists have nothing to configure, so `opts` only apply to `.pls` files.
+/// As with `write_to_path()`, only `.pls`, `.m3u`, and `.m3u8` are supported, and others, like `.xspf`, fail with `io::ErrorKind::InvalidInput`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// pls::write_to_path_with(&[PlaylistElement {
+///                             path: "Track 1.mp3".to_string(),
+///                             title: None,
+///                             len: ElementLength::Unknown,
+///                             genre: None,
+///                         }],
+///                         "Favourites.pls",
+///                         &WriteOptions::default().byte_order_mark(true))
+///     .unwrap();
+/// ```
+pub fn write_to_path_with<'i, S, I, P>(what: I, path: P, opts: &WriteOptions) -> io::Result<()>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          P: AsRef<Path>
+{
+    let path = path.as_ref();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("pls") => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_with(what, opts, &mut out)?;
+            out.flush()
+        }
+        Some(ext) if ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8") => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_m3u(what, &mut out)?;
+            out.flush()
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported playlist format for {}", path.display()))),
+    }
+}
+
+/// Write a playlist to the file at the specified path, replacing it atomically if it exists
+///
+/// The playlist is written to a new temporary file next to it, named `.{file name}.{process ID}.{counter}.tmp`,
+/// which is then renamed over it, so readers see either the old or the new playlist, never a truncated one,
+/// even if the process dies mid-write.
+/// Each call gets its own temporary file, so concurrent writes to the same path don't clobber one another; the last rename wins.
+/// The temporary file gets the permissions of the file being replaced, if there is one.
+/// `append_file()` replaces the file it appends to the same way.
+///
+/// Since the temporary file is in the same directory, the rename never crosses filesystems, so there's no fallback to copying,
+/// which couldn't be atomic anyway.
+/// The exception is a file that's a mount point of its own, like a bind-mounted one, which can't be renamed over:
+/// that fails, like with `io::ErrorKind::CrossesDevices` or `ResourceBusy`, leaving it as it was.
+///
+/// The temporary file is removed if anything fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength};
+/// pls::write_file_atomic(&[PlaylistElement {
+///                            path: "Track 1.mp3".to_string(),
+///                            title: None,
+///                            len: ElementLength::Unknown,
+///                            genre: None,
+///                        }],
+///                        "Now Playing.pls")
+///     .unwrap();
+/// ```
+pub fn write_file_atomic<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, P: AsRef<Path>>(what: I, path: P) -> io::Result<()> {
+    write_file_atomic_with(what, path, &WriteOptions::default())
+}
+
+/// Write a playlist to the file at the specified path, replacing it atomically, like `write_file_atomic()`, configured by the specified options
+///
+/// With `error_on_empty`, the temporary file is removed and the file being replaced is kept as-is if there are no elements.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pls::{PlaylistElement, ElementLength, WriteOptions};
+/// pls::write_file_atomic_with(&[PlaylistElement {
+///                                 path: "Track 1.mp3".to_string(),
+///                                 title: None,
+///                                 len: ElementLength::Unknown,
+///                                 genre: None,
+///                             }],
+///                             "Now Playing.pls",
+///                             &WriteOptions::default().emit_version(false))
+///     .unwrap();
+/// ```
+pub fn write_file_atomic_with<'i, S, I, P>(what: I, path: P, opts: &WriteOptions) -> io::Result<()>
+    where S: AsRef<str> + 'i,
+          I: IntoIterator<Item = &'i PlaylistElementGen<S>>,
+          P: AsRef<Path>
+{
+    replace_file(path.as_ref(), |out| write_with(what, opts, out))
+}
+
+
+/// Replace the file at `path` with what `write` writes, through a temporary file, like `write_file_atomic()` describes
+fn replace_file<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(path: &Path, write: F) -> io::Result<()> {
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} isn't a file path", path.display())))?;
+    let (temp, file) = loop {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(".{}.{}.tmp", process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let temp = path.with_file_name(temp_name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => break (temp, file),
+            // Left behind by an earlier process with the same ID
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    };
+
+    let result = (|| {
+        if let Ok(meta) = fs::metadata(path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        let mut out = BufWriter::new(file);
+        write(&mut out)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Write a whole playlist, for `write_with()` and `Playlist::write_with()`, with the name in a `PlaylistName` key, if any
+fn write_named<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(name: Option<&str>, what: I, opts: &WriteOptions,
+                                                                                                     to: &mut W)
+                                                                                                     -> io::Result<()> {
+    let mut what = what.into_iter().peekable();
+    if opts.error_on_empty && what.peek().is_none() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "refusing to write an empty playlist"));
+    }
+
+    write_header_with(opts, to)?;
+    if let Some(name) = name {
+        write!(to, "PlaylistName={}{}", name, opts.line_ending.as_str())?;
+    }
+
+    let mut ent = 0u64;
+    for elem in what {
+        write_entry_with(entry_index(ent, opts)?, elem, opts, to)?;
+        ent += 1;
+    }
+
+    write_footer_with(ent, opts, to)
+}
+
+/// Get the index to write the entry after `written` others with, by `start_index`
+fn entry_index(written: u64, opts: &WriteOptions) -> io::Result<u64> {
+    opts.start_index.checked_add(written).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "entry index overflows u64"))
+}
+
+/// Collect what `validate_for_write()` reports for the entry with the specified index
+fn value_warnings<S: AsRef<str>>(idx: u64, what: &PlaylistElementGen<S>, warnings: &mut Vec<WriteWarning>) {
+    let values = [("File", Some(what.path.as_ref())), ("Title", what.title.as_ref().map(S::as_ref)), ("Genre", what.genre.as_ref().map(S::as_ref))];
+    for &(prefix, value) in &values {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let key = format!("{}{}", prefix, idx);
+
+        if prefix == "File" && value.trim().is_empty() {
+            warnings.push(WriteWarning::EmptyPath { key });
+        } else if value.contains(&['\n', '\r'][..]) {
+            warnings.push(WriteWarning::LineBreak { key });
+        } else if value.trim() != value || value.ends_with('\0') || value.contains(&[';', '#'][..]) || value.starts_with(&['"', '\''][..]) {
+            warnings.push(WriteWarning::Altered { key });
+        }
+    }
+}
+
+/// Write just the keys of an entry, without the blank line after
+fn write_entry_keys<S: AsRef<str>, W: Write>(idx: u64, what: &PlaylistElementGen<S>, opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+    // With align_keys, all keys are padded to the width of the longest one, Length#
+    let digits = idx.to_string().len();
+    let width = if opts.align_keys { "Length".len() + digits } else { 0 };
+    let pad = |prefix: &str| width.saturating_sub(prefix.len() + digits);
+    let nl = opts.line_ending.as_str();
+
+    let mut written = [false; 4];
+    for &key in opts.key_order.iter().chain(EntryKey::ALL.iter()) {
+        if written[key as usize] {
+            continue;
+        }
+        written[key as usize] = true;
+
+        match key {
+            EntryKey::File => write!(to, "File{}{:w$}={}{}", idx, "", what.path.as_ref(), nl, w = pad("File"))?,
+            EntryKey::Title => {
+                if let Some(title) = what.title.as_ref() {
+                    write!(to, "Title{}{:w$}={}{}", idx, "", title.as_ref(), nl, w = pad("Title"))?;
+                }
+            }
+            EntryKey::Length => {
+                if let ElementLength::Seconds(s) = what.len {
+                    match opts.length_format {
+                        LengthFormat::Seconds => write!(to, "Length{}={}{}", idx, s, nl)?,
+                        LengthFormat::Clock if s < 60 * 60 => write!(to, "Length{}={}:{:02}{}", idx, s / 60, s % 60, nl)?,
+                        LengthFormat::Clock => write!(to, "Length{}={}:{:02}:{:02}{}", idx, s / (60 * 60), s / 60 % 60, s % 60, nl)?,
+                    }
+                }
+            }
+            EntryKey::Genre => {
+                if let Some(genre) = what.genre.as_ref() {
+                    write!(to, "Genre{}{:w$}={}{}", idx, "", genre.as_ref(), nl, w = pad("Genre"))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collapse runs of whitespace in `s` into single spaces, trimming it, for `write_canonical()`
+///
+/// `None` if nothing is left.
+fn canonical_text(s: &str) -> Option<String> {
+    let mut words = s.split_whitespace();
+    let mut text = words.next()?.to_string();
+    for word in words {
+        text.push(' ');
+        text.push_str(word);
+    }
+    Some(text)
+}
+
+/// Decode UTF-16 with the byte order specified by `unit`, replacing unpaired surrogates and a trailing odd byte with U+FFFD
+fn decode_utf16_lossy(data: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let mut ret = String::from_utf16_lossy(&data.chunks_exact(2).map(|c| unit([c[0], c[1]])).collect::<Vec<_>>());
+    if data.len() % 2 != 0 {
+        ret.push('\u{FFFD}');
+    }
+    ret
+}
+
+/// Decode all well-formed `%XX` escapes in `s`, leaving everything else as-is
+fn percent_decode(s: &str) -> Vec<u8> {
+    let s = s.as_bytes();
+    let hex = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+
+    let mut ret = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b'%' && i + 2 < s.len() {
+            if let (Some(hi), Some(lo)) = (hex(s[i + 1]), hex(s[i + 2])) {
+                ret.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+
+        ret.push(s[i]);
+        i += 1;
+    }
+    ret
+}
+
+/// Classify `path`, for `PlaylistElement::path_kind()`
+fn path_kind(path: &str) -> PathKind {
+    let bytes = path.as_bytes();
+    if bytes.starts_with(br"\\") {
+        PathKind::Unc
+    } else if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        PathKind::Drive
+    } else if is_file_url(path) {
+        PathKind::Url
+    } else {
+        match path.find("://") {
+            Some(i) if is_scheme(&path[..i]) => PathKind::Url,
+            _ => PathKind::Local,
+        }
+    }
+}
+
+/// Get the length of the UTF-8 byte order mark at the start of `data`, or 0 if there's none
+fn bom_len(data: &[u8]) -> usize {
+    if data.starts_with(b"\xEF\xBB\xBF") {
+        3
+    } else {
+        0
+    }
+}
+
+/// Check whether `s` starts with the `file:` scheme, case-insensitively
+fn is_file_url(s: &str) -> bool {
+    s.len() >= 5 && s.as_bytes()[..5].eq_ignore_ascii_case(b"file:")
+}
+
+/// Check whether `s` is a URL of a protocol only used for live streams
+fn is_live_url(s: &str) -> bool {
+    ["rtsp://", "mms://", "shout://"].iter().any(|p| s.len() >= p.len() && s.as_bytes()[..p.len()].eq_ignore_ascii_case(p.as_bytes()))
+}
+
+/// Check whether `s` is a valid URL scheme, as in RFC 3986
+fn is_scheme(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic()) && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+/// Take out the section called `name`, falling back to a case-insensitive match, picking the first one in lexical order if there
+/// are several
+fn take_section(mut ini: ini::Ini, name: &str) -> Option<ini::Properties> {
+    if let Some(section) = ini.delete(Some(name)) {
+        return Some(section);
+    }
+
+    let name = ini.sections().filter_map(|sname| sname.as_ref()).filter(|sname| sname.eq_ignore_ascii_case(name)).min().cloned();
+    name.and_then(|name| ini.delete(Some(name)))
+}
+
+/// Round a `Length#` value with a decimal fraction to the nearest second, halves up, saturating at `u64::MAX`
+///
+/// `None` unless the value is digits, a `.`, and more digits.
+fn round_fractional(value: &str) -> Option<Result<u64, ParseIntError>> {
+    let (whole, frac) = value.split_once('.')?;
+    if whole.is_empty() || frac.is_empty() || !whole.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(whole.parse::<u64>().map(|secs| if frac.as_bytes()[0] >= b'5' { secs.saturating_add(1) } else { secs }))
+}
+
+/// Strip a trailing `s` or `sec` unit, matched case-insensitively, and the whitespace before it, from a `Length#` value
+fn strip_length_unit(value: &str) -> &str {
+    for unit in &["sec", "s"] {
+        if let Some(i) = value.len().checked_sub(unit.len()) {
+            if i > 0 && value.is_char_boundary(i) && value[i..].eq_ignore_ascii_case(unit) {
+                return value[..i].trim_end();
+            }
+        }
+    }
+    value
+}
+
+/// Read a `Length#` value in the `M:SS` or `H:MM:SS` format, saturating at `u64::MAX`
+///
+/// `None` unless the value is digits followed by one or two `:` and two digits under 60 each.
+fn clock_length(value: &str) -> Option<Result<u64, ParseIntError>> {
+    let digits = |f: &str| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit());
+    let sexagesimal = |f: &str| if f.len() == 2 && digits(f) && f < "60" { f.parse::<u64>().ok() } else { None };
+
+    let (rest, secs) = value.rsplit_once(':')?;
+    let secs = sexagesimal(secs)?;
+    match rest.rsplit_once(':') {
+        Some((hours, mins)) => {
+            let mins = sexagesimal(mins)?;
+            if !digits(hours) {
+                return None;
+            }
+            Some(hours.parse::<u64>().map(|h| h.saturating_mul(60 * 60).saturating_add(mins * 60 + secs)))
+        }
+        None if digits(rest) => Some(rest.parse::<u64>().map(|m| m.saturating_mul(60).saturating_add(secs))),
+        None => None,
+    }
+}
+
+/// Read all of `what`, failing with `ParseError::TooLarge` if it's longer than `opts` allow
+fn read_input<R: Read>(what: &mut R, opts: &ParseOptions) -> Result<Vec<u8>, ParseError> {
+    let mut data = Vec::new();
+    match opts.max_bytes {
+        Some(max) => {
+            what.take((max as u64).saturating_add(1)).read_to_end(&mut data)?;
+            if data.len() > max {
+                return Err(ParseError::TooLarge(max));
+            }
+        }
+        None => {
+            what.read_to_end(&mut data)?;
+        }
+    }
+    Ok(data)
+}
+
+/// Get the name of the section `opts` read the playlist from
+fn section_name(opts: &ParseOptions) -> &str {
+    opts.section_name.as_ref().map(|s| &s[..]).unwrap_or("playlist")
+}
+
+/// Collect the indices of all `{prefix}#` keys for any of the `prefixes`, in ascending numeric order, without duplicates
+///
+/// Like `EntryIndex::key()` produces, only the canonical spelling of an index (no sign, no leading zeroes) is recognised.
+fn present_indices(section: &ini::Properties, prefixes: &[&str]) -> Vec<u64> {
+    let mut indices: Vec<u64> = section.keys()
+        .filter_map(|k| prefixes.iter().filter_map(|p| k.strip_prefix(p)).next())
+        .filter(|idx| !idx.is_empty() && !idx.starts_with('0') && idx.bytes().all(|b| b.is_ascii_digit()))
+        .filter_map(|idx| idx.parse().ok())
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// Generate a value for `Arbitrary` impls: 1-4 words of 1-8 characters, separated by single spaces
+#[cfg(feature = "arbitrary")]
+fn arbitrary_value(u: &mut Unstructured) -> arbitrary::Result<String> {
+    const CHARS: &[char] = &['a', 'b', 'c', 'k', 'x', 'z', 'A', 'F', 'R', 'O', '0', '1', '7', '9', '-', '_', '.', ',', '&', '(', ')', '!', '/',
+                             ':', '%', '@', '+', '~', 'é', 'ł', 'ß', 'м', 'у', 'ж', '音', '楽', '🎵'];
+
+    let mut ret = String::new();
+    for w in 0..u.int_in_range(1..=4)? {
+        if w != 0 {
+            ret.push(' ');
+        }
+        for _ in 0..u.int_in_range(1..=8)? {
+            ret.push(*u.choose(CHARS)?);
+        }
+    }
+    Ok(ret)
+}
+
+/// Get the name of the section `line` is the header of, if it is one, trimmed, like the INI backend reads it
+///
+/// Only whitespace and a comment may follow the `]`: the backend would read anything else as the start of a key.
+fn section_header(line: &str) -> Option<&str> {
+    let (name, rest) = line.trim().strip_prefix('[')?.split_once(']')?;
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with(';') || rest.starts_with('#') {
+        Some(name.trim())
+    } else {
+        None
+    }
+}
+
+/// Check whether `key` is an entry count key, i.e. `NumberOfEntries` or `NumberOfEvents`, in any capitalisation
+fn is_count_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case("NumberOfEntries") || key.eq_ignore_ascii_case("NumberOfEvents")
+}
+
+/// Check whether `key` is one `parse_full()` reads from the playlist section, for `parse_prefix()`
+fn is_playlist_key(key: &str) -> bool {
+    is_count_key(key) || ["Version", "PlaylistName", "Title"].contains(&key) ||
+    ["File", "Title", "Length", "Genre"]
+        .iter()
+        .filter_map(|p| key.strip_prefix(p))
+        .any(|idx| !idx.is_empty() && !idx.starts_with('0') && idx.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Parse the `value` of `key` as an integer
+fn parse_integer<T: FromStr<Err = ParseIntError>>(key: &str, value: &str) -> Result<T, ParseError> {
+    value.parse().map_err(|e| {
+        ParseError::InvalidInteger {
+            key: key.to_string(),
+            value: value.to_string(),
+            source: e,
+        }
+    })
+}
+
+/// Forwards to the wrapped writer, summing up the amount of bytes accepted
+struct CountingWriter<'w, W: Write + 'w> {
+    inner: &'w mut W,
+    count: usize,
+}
+
+impl<'w, W: Write> Write for CountingWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// An entry index, formatted once in base 10 into a stack buffer
+struct EntryIndex {
+    value: u64,
+    digits: [u8; 20],
+    start: usize,
+}
+
+impl EntryIndex {
+    fn new(value: u64) -> EntryIndex {
+        let mut idx = value;
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (idx % 10) as u8;
+            idx /= 10;
+            if idx == 0 {
+                break;
+            }
+        }
+
+        EntryIndex {
+            value,
+            digits,
+            start,
+        }
+    }
+
+    /// Format `{prefix}{idx}` into `buf`, reusing its allocation
+    fn key<'b>(&self, buf: &'b mut String, prefix: &str) -> &'b str {
+        buf.clear();
+        buf.push_str(prefix);
+        buf.extend(self.digits[self.start..].iter().map(|&d| d as char));
+        buf
+    }
+}
+
+
+impl<S: AsRef<str>> PlaylistElementGen<S> {
+    /// Get the path as a string slice
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// assert_eq!(PlaylistElement {
+    ///                    path: "Track 1.mp3".to_string(),
+    ///                    title: None,
+    ///                    len: ElementLength::Unknown,
+    ///                    genre: None,
+    ///                }
+    ///                .path_str(),
+    ///            "Track 1.mp3");
+    /// ```
+    pub fn path_str(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    /// Get the title as a string slice, or `""` if there's none
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// assert_eq!(PlaylistElement {
+    ///                    path: "Track 1.mp3".to_string(),
+    ///                    title: Some("Unknown Artist - Track 1".to_string()),
+    ///                    len: ElementLength::Unknown,
+    ///                    genre: None,
+    ///                }
+    ///                .title_str(),
+    ///            "Unknown Artist - Track 1");
+    /// assert_eq!(PlaylistElement {
+    ///                    path: "Track 2.mp3".to_string(),
+    ///                    title: None,
+    ///                    len: ElementLength::Unknown,
+    ///                    genre: None,
+    ///                }
+    ///                .title_str(),
+    ///            "");
+    /// ```
+    pub fn title_str(&self) -> &str {
+        self.title.as_ref().map(|t| t.as_ref()).unwrap_or("")
+    }
+
+    /// Check whether `query` is a substring of the file name (the part of the path after the last `/` or `\\`) or of the title
+    ///
+    /// With `case_insensitive`, both sides are lowercased first, following Unicode.
+    /// The empty query matches every element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// let elem = PlaylistElement {
+    ///     path: "S:/Music/Unknown Artist/Track 1.mp3".to_string(),
+    ///     title: Some("Unknown Artist - Track 1".to_string()),
+    ///     len: ElementLength::Unknown,
+    ///     genre: None,
+    /// };
+    /// assert!(elem.matches("Track 1", false));
+    /// assert!(elem.matches(".mp3", false));
+    /// assert!(elem.matches("unknown artist", true));
+    /// assert!(!elem.matches("unknown artist", false));
+    /// assert!(!elem.matches("Music", false));
+    /// ```
+    pub fn matches(&self, query: &str, case_insensitive: bool) -> bool {
+        let path = self.path_str();
+        let file_name = path.rfind(&['/', '\\'][..]).map(|i| &path[i + 1..]).unwrap_or(path);
+
+        if case_insensitive {
+            let query = query.to_lowercase();
+            file_name.to_lowercase().contains(&query) || self.title_str().to_lowercase().contains(&query)
+        } else {
+            file_name.contains(query) || self.title_str().contains(query)
+        }
+    }
+
+    /// Classify the path
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength, PathKind};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track 1.mp3").path_kind(), PathKind::Url);
+    /// assert_eq!(elem("file:Track 1.mp3").path_kind(), PathKind::Url);
+    /// assert_eq!(elem(r"\\Server\Music\Track 1.mp3").path_kind(), PathKind::Unc);
+    /// assert_eq!(elem(r"S:\Music\Track 1.mp3").path_kind(), PathKind::Drive);
+    /// assert_eq!(elem("S:Track 1.mp3").path_kind(), PathKind::Drive);
+    /// assert_eq!(elem("/srv/Music/Track 1.mp3").path_kind(), PathKind::Local);
+    /// assert_eq!(elem("Track 1.mp3").path_kind(), PathKind::Local);
+    /// ```
+    pub fn path_kind(&self) -> PathKind {
+        path_kind(self.path.as_ref())
+    }
+
+    /// Get the lowercased extension of the file the path points to, if it has one
+    ///
+    /// For URLs (per `is_url()`), the query and fragment are ignored, as is the host.
+    /// Like with `Path::extension()`, dotfiles like `.mp3` have no extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// assert_eq!(elem(r"S:\Music\Track 1.MP3").extension().as_deref(), Some("mp3"));
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track 1.ogg?sid=1").extension().as_deref(), Some("ogg"));
+    /// assert_eq!(elem("http://example.com").extension(), None);
+    /// assert_eq!(elem("Music.d/Track 1").extension(), None);
+    /// ```
+    pub fn extension(&self) -> Option<Cow<'_, str>> {
+        let mut path = self.path.as_ref();
+        if self.is_url() {
+            path = &path[..path.find(&['?', '#'][..]).unwrap_or(path.len())];
+            if let Some(i) = path.find("://") {
+                path = &path[i + 3 + path[i + 3..].find('/')?..];
+            }
+        }
+
+        let file_name = path.rfind(&['/', '\\'][..]).map(|i| &path[i + 1..]).unwrap_or(path);
+        let ext = match file_name.rfind('.') {
+            Some(0) | None => return None,
+            Some(i) => &file_name[i + 1..],
+        };
+        if ext.is_empty() {
+            None
+        } else if ext.bytes().any(|b| b.is_ascii_uppercase()) {
+            Some(Cow::Owned(ext.to_ascii_lowercase()))
+        } else {
+            Some(Cow::Borrowed(ext))
+        }
+    }
+
+    /// Guess what the path points to, based on its `extension()`
+    ///
+    /// See the variants of `MediaKind` for the extensions recognised.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength, MediaKind};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// assert_eq!(elem("Track 1.flac").media_kind(), MediaKind::Audio);
+    /// assert_eq!(elem("Track 1.mkv").media_kind(), MediaKind::Video);
+    /// assert_eq!(elem("http://127.0.0.1:8002/listen.pls?sid=1").media_kind(), MediaKind::Playlist);
+    /// assert_eq!(elem("http://127.0.0.1:8002/stream").media_kind(), MediaKind::Unknown);
+    /// ```
+    pub fn media_kind(&self) -> MediaKind {
+        match self.extension().as_deref() {
+            Some("aac") | Some("aif") | Some("aiff") | Some("ape") | Some("flac") | Some("m4a") | Some("mka") | Some("mp2") | Some("mp3") |
+            Some("oga") | Some("ogg") | Some("opus") | Some("wav") | Some("wma") | Some("wv") => MediaKind::Audio,
+            Some("avi") | Some("flv") | Some("m4v") | Some("mkv") | Some("mov") | Some("mp4") | Some("mpeg") | Some("mpg") | Some("ogv") |
+            Some("webm") | Some("wmv") => MediaKind::Video,
+            Some("asx") | Some("m3u") | Some("m3u8") | Some("pls") | Some("wpl") | Some("xspf") => MediaKind::Playlist,
+            _ => MediaKind::Unknown,
+        }
+    }
+
+    /// Check whether the path is a URL, rather than a local path
+    ///
+    /// That is, whether `path_kind()` is `PathKind::Url`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// assert!(PlaylistElement {
+    ///             path: "http://127.0.0.1:8002/Track 1.mp3".to_string(),
+    ///             title: None,
+    ///             len: ElementLength::Unknown,
+    ///             genre: None,
+    ///         }
+    ///         .is_url());
+    /// assert!(!PlaylistElement {
+    ///              path: "S:/Music/Track 1.mp3".to_string(),
+    ///              title: None,
+    ///              len: ElementLength::Unknown,
+    ///              genre: None,
+    ///          }
+    ///          .is_url());
+    /// ```
+    pub fn is_url(&self) -> bool {
+        self.path_kind() == PathKind::Url
+    }
+
+    /// Guess whether this is a live stream, as opposed to a file, local or remote
+    ///
+    /// That is, whether the path is a URL (per `is_url()`), other than a `file:` one, and the length is `Unknown`,
+    /// which is how internet radio playlists list their streams.
+    /// `rtsp://`, `mms://`, and `shout://` URLs are always streams, since those protocols are only used for them.
+    /// Callers with better knowledge (like the response's `Content-Length`) should prefer it over this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// # let elem = |p: &str, len| PlaylistElement { path: p.to_string(), title: None, len: len, genre: None };
+    /// assert!(elem("http://127.0.0.1:8002/stream", ElementLength::Unknown).is_stream());
+    /// assert!(elem("rtsp://127.0.0.1:8554/stream", ElementLength::Seconds(420)).is_stream());
+    /// assert!(!elem("http://127.0.0.1:8002/Track 1.mp3", ElementLength::Seconds(420)).is_stream());
+    /// assert!(!elem("file:///srv/Music/Track 1.mp3", ElementLength::Unknown).is_stream());
+    /// assert!(!elem("Track 1.mp3", ElementLength::Unknown).is_stream());
+    /// ```
+    pub fn is_stream(&self) -> bool {
+        is_live_url(self.path.as_ref()) || (self.len == ElementLength::Unknown && self.is_url() && !is_file_url(self.path.as_ref()))
+    }
+
+    /// Get the path with `%XX` escapes decoded, if it's a URL
+    ///
+    /// Paths that aren't URLs (per `is_url()`) are returned verbatim, as are malformed escapes, like `%` or `%G1`.
+    ///
+    /// If the decoded bytes aren't valid UTF-8 (as with URLs from servers using Latin-1),
+    /// the whole path is returned verbatim, still encoded; use `decoded_path_lossy()` to get it decoded anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track%201.mp3").decoded_path(), "http://127.0.0.1:8002/Track 1.mp3");
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track%FF.mp3").decoded_path(), "http://127.0.0.1:8002/Track%FF.mp3");
+    /// assert_eq!(elem("Track%201.mp3").decoded_path(), "Track%201.mp3");
+    /// ```
+    pub fn decoded_path(&self) -> Cow<'_, str> {
+        let path = self.path.as_ref();
+        if !self.is_url() || !path.contains('%') {
+            return Cow::Borrowed(path);
+        }
+
+        match String::from_utf8(percent_decode(path)) {
+            Ok(path) => Cow::Owned(path),
+            Err(_) => Cow::Borrowed(path),
+        }
+    }
+
+    /// Get the path with `%XX` escapes decoded, if it's a URL, replacing invalid UTF-8 with U+FFFD
+    ///
+    /// Otherwise the same as `decoded_path()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track%201.mp3").decoded_path_lossy(), "http://127.0.0.1:8002/Track 1.mp3");
+    /// assert_eq!(elem("http://127.0.0.1:8002/Track%FF.mp3").decoded_path_lossy(), "http://127.0.0.1:8002/Track\u{FFFD}.mp3");
+    /// ```
+    pub fn decoded_path_lossy(&self) -> Cow<'_, str> {
+        let path = self.path.as_ref();
+        if !self.is_url() || !path.contains('%') {
+            return Cow::Borrowed(path);
+        }
+
+        Cow::Owned(String::from_utf8_lossy(&percent_decode(path)).into_owned())
+    }
+
+    /// Replace the title, or remove it with `None`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// let mut elem = PlaylistElement {
+    ///     path: "Track 1.mp3".to_string(),
+    ///     title: None,
+    ///     len: ElementLength::Unknown,
+    ///     genre: None,
+    /// };
+    /// elem.set_title(Some("Unknown Artist - Track 1".to_string()));
+    /// assert_eq!(elem.title_str(), "Unknown Artist - Track 1");
+    /// elem.set_title(None);
+    /// assert_eq!(elem.title, None);
+    /// ```
+    pub fn set_title(&mut self, title: Option<S>) {
+        self.title = title;
+    }
+
+    /// Set the length to the specified amount of seconds
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// let mut elem = PlaylistElement {
+    ///     path: "Track 1.mp3".to_string(),
+    ///     title: None,
+    ///     len: ElementLength::Unknown,
+    ///     genre: None,
+    /// };
+    /// elem.set_length_secs(420);
+    /// assert_eq!(elem.len, ElementLength::Seconds(420));
+    /// ```
+    pub fn set_length_secs(&mut self, secs: u64) {
+        self.len = ElementLength::Seconds(secs);
+    }
+
+    /// Mark the length as `Unknown`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// let mut elem = PlaylistElement {
+    ///     path: "Track 1.mp3".to_string(),
+    ///     title: None,
+    ///     len: ElementLength::Seconds(420),
+    ///     genre: None,
+    /// };
+    /// elem.clear_length();
+    /// assert_eq!(elem.len, ElementLength::Unknown);
+    /// ```
+    pub fn clear_length(&mut self) {
+        self.len = ElementLength::Unknown;
+    }
+}
+
+impl PlaylistElement {
+    /// Build an element out of what a tag reader knows about a file, without a genre
+    ///
+    /// The path is converted lossily if it isn't valid Unicode, and sub-second precision is truncated, like in `fill_lengths()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{PlaylistElement, ElementLength};
+    /// # use std::time::Duration;
+    /// # struct Tag;
+    /// # impl Tag {
+    /// #     fn title(&self) -> Option<&str> { Some("Unknown Artist - Track 1") }
+    /// #     fn duration(&self) -> Option<u32> { Some(420_500) }
+    /// # }
+    /// # let tag = Tag;
+    /// // With the tag read by e.g. the id3 crate
+    /// let elem = PlaylistElement::from_parts("Music/Track 1.mp3",
+    ///                                        tag.title().map(str::to_string),
+    ///                                        tag.duration().map(|ms| Duration::from_millis(ms as u64)));
+    /// assert_eq!(elem,
+    ///            PlaylistElement {
+    ///                path: "Music/Track 1.mp3".to_string(),
+    ///                title: Some("Unknown Artist - Track 1".to_string()),
+    ///                len: ElementLength::Seconds(420),
+    ///                genre: None,
+    ///            });
+    /// ```
+    pub fn from_parts<P: AsRef<Path>>(path: P, title: Option<String>, duration: Option<Duration>) -> PlaylistElement {
+        PlaylistElement {
+            path: path.as_ref().to_string_lossy().into_owned(),
+            title,
+            len: duration.map(|d| ElementLength::Seconds(d.as_secs())).unwrap_or(ElementLength::Unknown),
+            genre: None,
+        }
+    }
+}
+
+impl ElementLength {
+    /// Check whether the length is known, i.e. `Seconds`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::ElementLength;
+    /// assert!(ElementLength::Seconds(420).is_known());
+    /// assert!(!ElementLength::Unknown.is_known());
+    /// ```
+    pub fn is_known(&self) -> bool {
+        matches!(*self, ElementLength::Seconds(_))
+    }
+
+    /// Check whether the length is `Unknown`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::ElementLength;
+    /// assert!(ElementLength::Unknown.is_unknown());
+    /// assert!(!ElementLength::Seconds(420).is_unknown());
+    /// ```
+    pub fn is_unknown(&self) -> bool {
+        *self == ElementLength::Unknown
+    }
+
+    /// Get the amount of seconds, or `None` if `Unknown`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::ElementLength;
+    /// assert_eq!(ElementLength::Seconds(420).seconds(), Some(420));
+    /// assert_eq!(ElementLength::Unknown.seconds(), None);
+    /// ```
+    pub fn seconds(&self) -> Option<u64> {
+        match *self {
+            ElementLength::Seconds(s) => Some(s),
+            ElementLength::Unknown => None,
+        }
+    }
+
+    /// Get the amount of seconds, or `default` if `Unknown`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::ElementLength;
+    /// assert_eq!(ElementLength::Seconds(420).unwrap_or(0), 420);
+    /// assert_eq!(ElementLength::Unknown.unwrap_or(0), 0);
+    /// ```
+    pub fn unwrap_or(self, default: u64) -> u64 {
+        self.seconds().unwrap_or(default)
+    }
+}
+
+impl Playlist {
+    /// Write the playlist like `write()`, with the name, if any, in a `PlaylistName` key right after the header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{Playlist, PlaylistElement, ElementLength};
+    /// let mut buf = Vec::new();
+    /// Playlist {
+    ///         name: Some("Favourites".to_string()),
+    ///         entries: vec![PlaylistElement {
+    ///                           path: "Track 1.mp3".to_string(),
+    ///                           title: None,
+    ///                           len: ElementLength::Seconds(420),
+    ///                           genre: None,
+    ///                       }],
+    ///     }
+    ///     .write(&mut buf)
+    ///     .unwrap();
+    /// assert_eq!(String::from_utf8(buf).unwrap(),
+    ///            "[playlist]\n\
+    ///             PlaylistName=Favourites\n\
+    ///             File1=Track 1.mp3\n\
+    ///             Length1=420\n\
+    ///             \n\
+    ///             NumberOfEntries=1\n\
+    ///             Version=2\n");
+    /// ```
+    pub fn write<W: Write>(&self, to: &mut W) -> io::Result<()> {
+        self.write_with(&WriteOptions::default(), to)
+    }
+
+    /// Write the playlist like `write_with()`, with the name, if any, in a `PlaylistName` key right after the header
+    ///
+    /// `write()` is this with the default options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{Playlist, PlaylistElement, ElementLength, LineEnding, WriteOptions};
+    /// let mut buf = Vec::new();
+    /// Playlist {
+    ///         name: Some("Favourites".to_string()),
+    ///         entries: vec![PlaylistElement {
+    ///                           path: "Track 1.mp3".to_string(),
+    ///                           title: None,
+    ///                           len: ElementLength::Seconds(420),
+    ///                           genre: None,
+    ///                       }],
+    ///     }
+    ///     .write_with(&WriteOptions::default().line_ending(LineEnding::CrLf).emit_version(false), &mut buf)
+    ///     .unwrap();
+    /// assert_eq!(String::from_utf8(buf).unwrap(),
+    ///            "[playlist]\r\n\
+    ///             PlaylistName=Favourites\r\n\
+    ///             File1=Track 1.mp3\r\n\
+    ///             Length1=420\r\n\
+    ///             \r\n\
+    ///             NumberOfEntries=1\r\n");
+    /// ```
+    pub fn write_with<W: Write>(&self, opts: &WriteOptions, to: &mut W) -> io::Result<()> {
+        write_named(self.name.as_ref().map(|n| &n[..]), &self.entries, opts, to)
+    }
+
+    /// Iterate over the entries along with the (1-based) `File#` index each one gets on `write()`, like for a numbered list
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{Playlist, PlaylistElement, ElementLength};
+    /// # let elem = |t: &str| PlaylistElement { path: format!("{}.mp3", t), title: Some(t.to_string()), len: ElementLength::Unknown, genre: None };
+    /// let playlist = Playlist {
+    ///     name: None,
+    ///     entries: vec![elem("Stronger"), elem("Animal Kingdom"), elem("Activated Trap Locks")],
+    /// };
+    /// let listing: Vec<_> = playlist.entries_with_index().map(|(i, e)| format!("{}. {}", i, e.title_str())).collect();
+    /// assert_eq!(listing, ["1. Stronger", "2. Animal Kingdom", "3. Activated Trap Locks"]);
+    ///
+    /// let mut buf = Vec::new();
+    /// playlist.write(&mut buf).unwrap();
+    /// assert!(String::from_utf8(buf).unwrap().contains("\nFile3=Activated Trap Locks.mp3\n"));
+    /// ```
+    pub fn entries_with_index(&self) -> impl Iterator<Item = (usize, &PlaylistElement)> + '_ {
+        self.entries.iter().enumerate().map(|(i, e)| (i + 1, e))
+    }
+
+    /// Insert an element at the 0-based position `index`, shifting the ones after it, or at the end if `index` is past it
+    ///
+    /// Positions aren't stable IDs: the elements after it each get their `File#` index bumped on `write()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{Playlist, PlaylistElement, ElementLength};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// let mut playlist = Playlist { name: None, entries: vec![elem("Track 1.mp3"), elem("Track 3.mp3")] };
+    /// playlist.insert_at(1, elem("Track 2.mp3"));
+    /// playlist.insert_at(420, elem("Track 4.mp3"));
+    /// assert_eq!(pls::paths(&playlist.entries), ["Track 1.mp3", "Track 2.mp3", "Track 3.mp3", "Track 4.mp3"]);
+    /// ```
+    pub fn insert_at(&mut self, index: usize, element: PlaylistElement) {
+        let index = cmp::min(index, self.entries.len());
+        self.entries.insert(index, element);
+    }
+
+    /// Remove the element at the 0-based position `index`, shifting the ones after it, or return `None` if there's none there
+    ///
+    /// Positions aren't stable IDs: the elements after it each get their `File#` index decremented on `write()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::{Playlist, PlaylistElement, ElementLength};
+    /// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+    /// let mut playlist = Playlist { name: None, entries: vec![elem("Track 1.mp3"), elem("Track 2.mp3")] };
+    /// assert_eq!(playlist.remove_at(0), Some(elem("Track 1.mp3")));
+    /// assert_eq!(playlist.remove_at(1), None);
+    /// assert_eq!(pls::paths(&playlist.entries), ["Track 2.mp3"]);
+    /// ```
+    pub fn remove_at(&mut self, index: usize) -> Option<PlaylistElement> {
+        if index < self.entries.len() {
+            Some(self.entries.remove(index))
+        } else {
+            None
+        }
+    }
+}
+
+/// Human-readable summary, for logging and debugging, not the on-disk format
+///
+/// The path, followed by ` (title)` if there is a title, followed by ` [Ns]` if the length is known.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(PlaylistElement {
+///                    path: "Track 1.mp3".to_string(),
+///                    title: Some("Unknown Artist - Track 1".to_string()),
+///                    len: ElementLength::Seconds(420),
+///                    genre: None,
+///                }
+///                .to_string(),
+///            "Track 1.mp3 (Unknown Artist - Track 1) [420s]");
+/// assert_eq!(PlaylistElement {
+///                    path: "Track 2.mp3".to_string(),
+///                    title: None,
+///                    len: ElementLength::Seconds(420),
+///                    genre: None,
+///                }
+///                .to_string(),
+///            "Track 2.mp3 [420s]");
+/// assert_eq!(PlaylistElement {
+///                    path: "Track 3.mp3".to_string(),
+///                    title: Some("Unknown Artist - Track 3".to_string()),
+///                    len: ElementLength::Unknown,
+///                    genre: None,
+///                }
+///                .to_string(),
+///            "Track 3.mp3 (Unknown Artist - Track 3)");
+/// assert_eq!(PlaylistElement {
+///                    path: "Track 4.mp3".to_string(),
+///                    title: None,
+///                    len: ElementLength::Unknown,
+///                    genre: None,
+///                }
+///                .to_string(),
+///            "Track 4.mp3");
+/// ```
+impl<S: AsRef<str>> fmt::Display for PlaylistElementGen<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.path.as_ref())?;
+        if let Some(title) = self.title.as_ref() {
+            write!(f, " ({})", title.as_ref())?;
+        }
+        if let ElementLength::Seconds(s) = self.len {
+            write!(f, " [{}s]", s)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// An element with the specified path and length, and no title or genre
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(PlaylistElement::from(("Track 1.mp3".to_string(), ElementLength::Seconds(420))),
+///            PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            });
+/// ```
+impl From<(String, ElementLength)> for PlaylistElement {
+    fn from((path, len): (String, ElementLength)) -> PlaylistElement {
+        PlaylistElement {
+            path,
+            title: None,
+            len,
+            genre: None,
+        }
+    }
+}
+
+/// An element built by `PlaylistElement::from_parts()`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::path::PathBuf;
+/// assert_eq!(PlaylistElement::from((PathBuf::from("Track 1.mp3"), None, None)),
+///            PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: None,
+///                len: ElementLength::Unknown,
+///                genre: None,
+///            });
+/// ```
+impl From<(PathBuf, Option<String>, Option<Duration>)> for PlaylistElement {
+    fn from((path, title, duration): (PathBuf, Option<String>, Option<Duration>)) -> PlaylistElement {
+        PlaylistElement::from_parts(path, title, duration)
+    }
+}
+
+/// Copy the strings of a borrowed element
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, PlaylistElementRef, ElementLength};
+/// assert_eq!(PlaylistElement::from(PlaylistElementRef {
+///                path: "Track 1.mp3",
+///                title: Some("Unknown Artist - Track 1"),
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            }),
+///            PlaylistElement {
+///                path: "Track 1.mp3".to_string(),
+///                title: Some("Unknown Artist - Track 1".to_string()),
+///                len: ElementLength::Seconds(420),
+///                genre: None,
+///            });
+/// ```
+impl<'s> From<PlaylistElementRef<'s>> for PlaylistElement {
+    fn from(elem: PlaylistElementRef<'s>) -> PlaylistElement {
+        PlaylistElement {
+            path: elem.path.to_string(),
+            title: elem.title.map(str::to_string),
+            len: elem.len,
+            genre: elem.genre.map(str::to_string),
+        }
+    }
+}
+
+/// Random elements for fuzzing and property testing, that survive a `write()`-`parse()` round-trip unchanged
+///
+/// Paths, titles, and genres are a few words of letters, digits, and punctuation, without line breaks, surrounding whitespace,
+/// or characters with a special meaning in INI files.
+///
+/// Requires the `arbitrary` feature.
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for PlaylistElement {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<PlaylistElement> {
+        Ok(PlaylistElement {
+            path: arbitrary_value(u)?,
+            title: if u.arbitrary()? { Some(arbitrary_value(u)?) } else { None },
+            len: u.arbitrary()?,
+            genre: if u.arbitrary()? { Some(arbitrary_value(u)?) } else { None },
+        })
+    }
+}
+
+/// Random lengths for fuzzing and property testing, either `Unknown` or any amount of `Seconds`
+///
+/// Requires the `arbitrary` feature.
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for ElementLength {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<ElementLength> {
+        Ok(if u.arbitrary()? {
+            ElementLength::Seconds(u.arbitrary()?)
+        } else {
+            ElementLength::Unknown
+        })
+    }
+}
+
+/// Parse the value of a `Length#` key: `-1` is `Unknown`, any other integer is `Seconds`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ElementLength;
+/// assert_eq!("420".parse(), Ok(ElementLength::Seconds(420)));
+/// assert_eq!("-1".parse(), Ok(ElementLength::Unknown));
+/// assert!("-2".parse::<ElementLength>().is_err());
+/// assert!("Unknown".parse::<ElementLength>().is_err());
+/// ```
+impl FromStr for ElementLength {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<ElementLength, ParseIntError> {
+        if s == "-1" {
+            Ok(ElementLength::Unknown)
+        } else {
+            Ok(ElementLength::Seconds(s.parse()?))
+        }
+    }
+}
+
+/// Same as `FromStr`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ElementLength;
+/// # use std::convert::TryFrom;
+/// assert_eq!(ElementLength::try_from("420"), Ok(ElementLength::Seconds(420)));
+/// assert_eq!(ElementLength::try_from("-1"), Ok(ElementLength::Unknown));
+/// ```
+impl<'s> TryFrom<&'s str> for ElementLength {
+    type Error = ParseIntError;
+
+    fn try_from(s: &'s str) -> Result<ElementLength, ParseIntError> {
+        s.parse()
+    }
+}
+
+/// `Seconds` if `Some`, `Unknown` if `None`, like a nullable column
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ElementLength;
+/// assert_eq!(ElementLength::from(Some(420)), ElementLength::Seconds(420));
+/// assert_eq!(ElementLength::from(None), ElementLength::Unknown);
+/// ```
+impl From<Option<u64>> for ElementLength {
+    fn from(secs: Option<u64>) -> ElementLength {
+        match secs {
+            Some(s) => ElementLength::Seconds(s),
+            None => ElementLength::Unknown,
+        }
+    }
+}
+
+/// Same as `ElementLength::seconds()`
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ElementLength;
+/// assert_eq!(Option::<u64>::from(ElementLength::Seconds(420)), Some(420));
+/// let secs: Option<u64> = ElementLength::Unknown.into();
+/// assert_eq!(secs, None);
+/// ```
+impl From<ElementLength> for Option<u64> {
+    fn from(len: ElementLength) -> Option<u64> {
+        len.seconds()
+    }
+}
+
+
+impl ParseError {
+    /// Get the 0-based line the error occurred on, if known
+    ///
+    /// Only `Ini` syntax errors carry a location, all other errors return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let err = pls::parse(&mut &b"[playlist]\n[playlist"[..]).unwrap_err();
+    /// assert_eq!(err.line(), Some(1));
+    /// assert_eq!(err.column(), Some(9));
+    ///
+    /// let err = pls::parse(&mut &b"[playlist]\n"[..]).unwrap_err();
+    /// assert_eq!(err.line(), None);
+    /// assert_eq!(err.column(), None);
+    /// ```
+    pub fn line(&self) -> Option<usize> {
+        self.location().map(|(line, _)| line)
+    }
+
+    /// Get the 0-based column the error occurred at, if known
+    ///
+    /// Available under the same conditions as `line()`.
+    pub fn column(&self) -> Option<usize> {
+        self.location().map(|(_, col)| col)
+    }
+
+    fn location(&self) -> Option<(usize, usize)> {
+        match *self {
+            ParseError::Ini(ref e) => Some((e.line, e.col)),
+            _ => None,
+        }
+    }
+}
+
+impl From<ini::Error> for ParseError {
+    fn from(e: ini::Error) -> ParseError {
+        ParseError::Ini(e)
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> ParseError {
+        ParseError::Io(e.kind())
+    }
+}
+
+impl ErrorT for ParseError {
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        match *self {
+            ParseError::InvalidVersion(_) => "invalid version specified",
+            ParseError::Empty => "empty input",
+            ParseError::MissingPlaylistSection => "[playlist] section missing",
+            ParseError::MissingKey { .. } => "required key missing",
+            ParseError::ConflictingCountKeys => "conflicting entry counts",
+            ParseError::InvalidInteger { ref source, .. } => source.description(),
+            ParseError::LengthOverflow { .. } => "length too big",
+            ParseError::TooLarge(_) => "input too large",
+            ParseError::Utf8(_) => "input not UTF-8",
+            ParseError::Ini(ref e) => e.description(),
+            ParseError::Io(_) => "I/O error",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn ErrorT + 'static)> {
+        match *self {
+            ParseError::InvalidInteger { ref source, .. } => Some(source),
+            ParseError::Ini(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InvalidVersion(v) => write!(f, "Invalid version {} specified", v),
+            ParseError::Empty => write!(f, "Empty input"),
+            ParseError::MissingPlaylistSection => write!(f, "Missing [playlist] section"),
+            ParseError::MissingKey { ref key, entry: Some(i) } => write!(f, "Entry {} missing key \"{}\"", i, key),
+            ParseError::MissingKey { ref key, entry: None } => write!(f, "Key \"{}\" missing", key),
+            ParseError::ConflictingCountKeys => write!(f, "Conflicting NumberOfEntries|numberofentries|NumberOfEvents values"),
+            ParseError::InvalidInteger { ref key, ref value, ref source } => write!(f, "Key \"{}\" has invalid integer value \"{}\": {}", key, value, source),
+            ParseError::LengthOverflow { ref key, ref value } => write!(f, "Key \"{}\" has length \"{}\" too big to represent", key, value),
+            ParseError::TooLarge(max) => write!(f, "Input longer than {} bytes", max),
+            ParseError::Utf8(at) => write!(f, "Input not valid UTF-8 at byte {}", at),
+            ParseError::Ini(ref e) => e.fmt(f),
+            ParseError::Io(kind) => kind.fmt(f),
+        }
+    }
+}
+
+impl Clone for ParseError {
+    fn clone(&self) -> ParseError {
+        match *self {
+            ParseError::InvalidVersion(v) => ParseError::InvalidVersion(v),
+            ParseError::Empty => ParseError::Empty,
+            ParseError::MissingPlaylistSection => ParseError::MissingPlaylistSection,
+            ParseError::MissingKey { ref key, entry } => {
+                ParseError::MissingKey {
+                    key: key.clone(),
+                    entry,
+                }
+            }
+            ParseError::ConflictingCountKeys => ParseError::ConflictingCountKeys,
+            ParseError::InvalidInteger { ref key, ref value, ref source } => {
+                ParseError::InvalidInteger {
+                    key: key.clone(),
+                    value: value.clone(),
+                    source: source.clone(),
+                }
+            }
+            ParseError::LengthOverflow { ref key, ref value } => {
+                ParseError::LengthOverflow {
+                    key: key.clone(),
+                    value: value.clone(),
+                }
+            }
+            ParseError::TooLarge(max) => ParseError::TooLarge(max),
+            ParseError::Utf8(at) => ParseError::Utf8(at),
+            ParseError::Ini(ref e) => ParseError::Ini(ini::Error { msg: e.msg.clone(), ..*e }),
+            ParseError::Io(kind) => ParseError::Io(kind),
+        }
+    }
+}
+
+impl PartialEq for ParseError {
+    fn eq(&self, rhs: &ParseError) -> bool {
+        match (self, rhs) {
+            (ParseError::InvalidVersion(v), ParseError::InvalidVersion(rv)) => v == rv,
+            (ParseError::Empty, ParseError::Empty) => true,
+            (ParseError::MissingPlaylistSection, ParseError::MissingPlaylistSection) => true,
+            (ParseError::MissingKey { key, entry }, ParseError::MissingKey { key: rkey, entry: rentry }) => key == rkey && entry == rentry,
+            (ParseError::ConflictingCountKeys, ParseError::ConflictingCountKeys) => true,
+            (ParseError::InvalidInteger { key, value, source }, ParseError::InvalidInteger { key: rkey, value: rvalue, source: rsource }) => {
+                key == rkey && value == rvalue && source == rsource
+            }
+            (ParseError::LengthOverflow { key, value }, ParseError::LengthOverflow { key: rkey, value: rvalue }) => key == rkey && value == rvalue,
+            (ParseError::TooLarge(max), ParseError::TooLarge(rmax)) => max == rmax,
+            (ParseError::Utf8(at), ParseError::Utf8(rat)) => at == rat,
+            (ParseError::Ini(e), ParseError::Ini(re)) => e.line == re.line && e.col == re.col && e.msg == re.msg,
+            (ParseError::Io(k), ParseError::Io(rk)) => k == rk,
+            (_, _) => false,
+        }
+    }
+}
+
+/// Human-readable description, like `ParseError`'s
+///
+/// # Examples
+///
+/// ```
+/// # use pls::WriteWarning;
+/// assert_eq!(WriteWarning::LineBreak { key: "Title3".to_string() }.to_string(), "Line break in key \"Title3\"");
+/// ```
+impl fmt::Display for WriteWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WriteWarning::EmptyPath { ref key } => write!(f, "Empty path in key \"{}\"", key),
+            WriteWarning::LineBreak { ref key } => write!(f, "Line break in key \"{}\"", key),
+            WriteWarning::Altered { ref key } => write!(f, "Value of key \"{}\" won't read back the same", key),
         }
     }
 }
@@ -0,0 +1,170 @@
+//! Converting and writing M3U playlists
+
+
+use super::{PlaylistElement, PlaylistElementGen, ElementLength, Writer, round_fractional};
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+
+/// Convert an M3U (or extended M3U) playlist to a PLS one, line by line, never holding more than one element in memory
+///
+/// Every line that isn't blank and doesn't start with a `#` is a path, and becomes an element, written like by `Writer`.
+/// Directives before a path apply to it:
+///
+///   * `#EXTINF:length,title` sets the length and title:
+///     a negative length, like `-1`, or one that doesn't parse is `Unknown`, a fractional one is rounded to the nearest second,
+///     and an empty title is `None`; attributes between the length and the comma, like `tvg-id="..."`, are skipped,
+///   * `#EXTGENRE:genre` sets the genre.
+///
+/// Other `#` lines, like `#EXTM3U`, are ignored, as are a leading byte order mark and whitespace around lines.
+///
+/// Fails if reading or writing does, including if the input isn't valid UTF-8, leaving the output incomplete.
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = Vec::new();
+/// pls::transcode_m3u_to_pls(&mut &b"#EXTM3U\n\
+///                                   #EXTINF:420,Unknown Artist - Track 1\n\
+///                                   Track 1.mp3\n\
+///                                   http://127.0.0.1:8002/stream\n"[..],
+///                           &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             Title1=Unknown Artist - Track 1\n\
+///             Length1=420\n\
+///             \n\
+///             File2=http://127.0.0.1:8002/stream\n\
+///             \n\
+///             NumberOfEntries=2\n\
+///             Version=2\n");
+/// ```
+pub fn transcode_m3u_to_pls<R: Read, W: Write>(from: &mut R, to: &mut W) -> io::Result<()> {
+    let mut writer = Writer::new(to);
+    let mut info = None;
+    let mut genre = None;
+    for (i, line) in BufReader::new(from).lines().enumerate() {
+        let line = line?;
+        let line = if i == 0 { line.trim_start_matches('\u{FEFF}') } else { &line[..] }.trim();
+
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            info = Some(extinf_fields(extinf));
+        } else if let Some(g) = line.strip_prefix("#EXTGENRE:") {
+            genre = Some(g.trim().to_string()).filter(|g| !g.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (len, title) = info.take().unwrap_or((ElementLength::Unknown, None));
+            writer.push(&PlaylistElement {
+                    path: line.to_string(),
+                    title,
+                    len,
+                    genre: genre.take(),
+                })?;
+        }
+    }
+    writer.finish().map(|_| ())
+}
+
+/// Write a playlist as an extended M3U one, which, since it's always UTF-8, is also an M3U8 one
+///
+/// The output starts with `#EXTM3U`, and each element is its path on a line of its own, preceded by
+///
+///   * `#EXTINF:length,title`, if it has a title or a length, with `-1` for an `Unknown` length and nothing after the comma for no title,
+///   * `#EXTGENRE:genre`, if it has a genre,
+///
+/// which `transcode_m3u_to_pls()` reads back.
+///
+/// A line break can't be written in any of those, so an element with a `\n` or `\r` in its path, title, or genre
+/// fails with `io::ErrorKind::InvalidInput`, before it's written, leaving the output incomplete.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::io;
+/// let mut buf = Vec::new();
+/// pls::write_m3u(&[PlaylistElement {
+///                    path: "Track 1.mp3".to_string(),
+///                    title: Some("Unknown Artist - Track 1".to_string()),
+///                    len: ElementLength::Unknown,
+///                    genre: Some("Rock".to_string()),
+///                },
+///                PlaylistElement {
+///                    path: "http://127.0.0.1:8002/stream".to_string(),
+///                    title: None,
+///                    len: ElementLength::Unknown,
+///                    genre: None,
+///                }],
+///                &mut buf)
+///     .unwrap();
+/// assert_eq!(String::from_utf8(buf).unwrap(),
+///            "#EXTM3U\n\
+///             #EXTINF:-1,Unknown Artist - Track 1\n\
+///             #EXTGENRE:Rock\n\
+///             Track 1.mp3\n\
+///             http://127.0.0.1:8002/stream\n");
+///
+/// assert_eq!(pls::write_m3u(&[PlaylistElement {
+///                               path: "Track 1.mp3\n#EXTINF:1,Injected".to_string(),
+///                               title: None,
+///                               len: ElementLength::Unknown,
+///                               genre: None,
+///                           }],
+///                           &mut Vec::new())
+///                .unwrap_err()
+///                .kind(),
+///            io::ErrorKind::InvalidInput);
+/// ```
+pub fn write_m3u<'i, S: AsRef<str> + 'i, I: IntoIterator<Item = &'i PlaylistElementGen<S>>, W: Write>(what: I, to: &mut W) -> io::Result<()> {
+    writeln!(to, "#EXTM3U")?;
+    for elem in what {
+        let title = elem.title.as_ref().map_or("", |t| t.as_ref());
+        let genre = elem.genre.as_ref().map(|g| g.as_ref());
+        for &(field, value) in &[("path", elem.path.as_ref()), ("title", title), ("genre", genre.unwrap_or(""))] {
+            if value.contains(&['\n', '\r'][..]) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("line break in M3U element {}: {:?}", field, value)));
+            }
+        }
+
+        match elem.len {
+            ElementLength::Seconds(s) => writeln!(to, "#EXTINF:{},{}", s, title)?,
+            ElementLength::Unknown if elem.title.is_some() => writeln!(to, "#EXTINF:-1,{}", title)?,
+            ElementLength::Unknown => {}
+        }
+        if let Some(genre) = genre {
+            writeln!(to, "#EXTGENRE:{}", genre)?;
+        }
+        writeln!(to, "{}", elem.path.as_ref())?;
+    }
+    Ok(())
+}
+
+
+/// Read the length and title out of the rest of an `#EXTINF:` line
+fn extinf_fields(extinf: &str) -> (ElementLength, Option<String>) {
+    // The title follows the first comma that's not in a quoted attribute value
+    let mut quoted = false;
+    let comma = extinf.char_indices()
+        .find(|&(_, c)| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ',' && !quoted
+        })
+        .map(|(i, _)| i);
+    let (head, title) = match comma {
+        Some(i) => (&extinf[..i], extinf[i + 1..].trim()),
+        None => (extinf, ""),
+    };
+
+    let length = head.split_whitespace().next().unwrap_or("");
+    let len = if length.starts_with('-') {
+        None
+    } else {
+        match round_fractional(length) {
+            Some(secs) => secs.ok(),
+            None => length.parse().ok(),
+        }
+    };
+    (len.map_or(ElementLength::Unknown, ElementLength::Seconds), Some(title.to_string()).filter(|t| !t.is_empty()))
+}
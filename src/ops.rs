@@ -0,0 +1,739 @@
+//! Operations on whole playlists
+
+
+use super::{PlaylistElement, NormalizeOptions, ElementLength, PathKind, percent_decode, is_file_url, path_kind};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "url")]
+use url::Url;
+#[cfg(feature = "encoding")]
+use encoding_rs::WINDOWS_1252;
+
+
+/// Summary of a whole playlist, as returned by `stats()`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct PlaylistStats {
+    /// Amount of elements
+    pub count: usize,
+    /// Amount of elements with a known length
+    pub known_lengths: usize,
+    /// Amount of elements with an `Unknown` length
+    pub unknown_lengths: usize,
+    /// Sum of all known lengths, saturating at `Duration::MAX`, like for two of `u64::MAX` seconds, which overflowing ones are clamped to
+    pub total_known: Duration,
+    /// Whether any element's path is a URL
+    pub has_urls: bool,
+    /// Whether any element's path is a local path
+    pub has_local: bool,
+}
+
+
+/// Fill in the lengths of all elements whose length is `Unknown` from the callback
+///
+/// Elements with a known length are left untouched, as are ones for which the callback returns `None`.
+///
+/// Sub-second precision is truncated, since `Length#` is specified in whole seconds.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::time::Duration;
+/// let mut elements = vec![PlaylistElement {
+///                             path: "Track 1.mp3".to_string(),
+///                             title: None,
+///                             len: ElementLength::Unknown,
+///                             genre: None,
+///                         },
+///                         PlaylistElement {
+///                             path: "Track 2.mp3".to_string(),
+///                             title: None,
+///                             len: ElementLength::Seconds(420),
+///                             genre: None,
+///                         }];
+/// pls::fill_lengths(&mut elements, |_| Some(Duration::from_millis(123456)));
+/// assert_eq!(elements[0].len, ElementLength::Seconds(123));
+/// assert_eq!(elements[1].len, ElementLength::Seconds(420));
+/// ```
+pub fn fill_lengths<F: FnMut(&PlaylistElement) -> Option<Duration>>(elements: &mut [PlaylistElement], mut f: F) {
+    for elem in elements.iter_mut().filter(|e| e.len == ElementLength::Unknown) {
+        if let Some(d) = f(elem) {
+            elem.len = ElementLength::Seconds(d.as_secs());
+        }
+    }
+}
+
+/// Fill in missing titles and `Unknown` lengths of `target` elements from the `source` elements with the same path
+///
+/// Paths have to match exactly, byte for byte; if several `source` elements have the same path, the first one is used.
+/// Titles and lengths already in `target` are never overwritten, and `source` elements with no match are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str, t: Option<&str>, l| PlaylistElement { path: p.to_string(), title: t.map(str::to_string), len: l, genre: None };
+/// let master = [elem("Track 1.mp3", Some("Unknown Artist - Track 1"), ElementLength::Seconds(420)),
+///               elem("Track 2.mp3", Some("Unknown Artist - Track 2"), ElementLength::Seconds(180))];
+/// let mut device = vec![elem("Track 2.mp3", Some("Track 2"), ElementLength::Unknown),
+///                       elem("Track 3.mp3", None, ElementLength::Unknown)];
+///
+/// pls::enrich_from(&mut device, &master);
+/// assert_eq!(device,
+///            vec![elem("Track 2.mp3", Some("Track 2"), ElementLength::Seconds(180)),
+///                 elem("Track 3.mp3", None, ElementLength::Unknown)]);
+/// ```
+pub fn enrich_from(target: &mut [PlaylistElement], source: &[PlaylistElement]) {
+    let mut by_path = HashMap::with_capacity(source.len());
+    for elem in source {
+        by_path.entry(&elem.path[..]).or_insert(elem);
+    }
+
+    for elem in target {
+        if let Some(src) = by_path.get(&elem.path[..]) {
+            if elem.title.is_none() {
+                elem.title.clone_from(&src.title);
+            }
+            if elem.len == ElementLength::Unknown {
+                elem.len = src.len;
+            }
+        }
+    }
+}
+
+/// Find the elements matching a search box `query`, case-insensitively, in order
+///
+/// See `PlaylistElement::matches()` for what's searched.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str, t: &str| PlaylistElement { path: p.to_string(), title: Some(t.to_string()), len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("Track 1.mp3", "Unknown Artist - Opening"),
+///                 elem("Track 2.mp3", "Other Artist - Interlude"),
+///                 elem("Track 3.mp3", "Unknown Artist - Closing")];
+/// assert_eq!(pls::search(&elements, "UNKNOWN"), [&elements[0], &elements[2]]);
+/// assert_eq!(pls::search(&elements, "track 2"), [&elements[1]]);
+/// ```
+pub fn search<'e>(elements: &'e [PlaylistElement], query: &str) -> Vec<&'e PlaylistElement> {
+    elements.iter().filter(|e| e.matches(query, true)).collect()
+}
+
+/// Summarise a playlist in a single pass
+///
+/// URLs are told apart from local paths with `PlaylistElement::is_url()`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, PlaylistStats, ElementLength};
+/// # use std::time::Duration;
+/// assert_eq!(pls::stats(&[PlaylistElement {
+///                            path: "Track 1.mp3".to_string(),
+///                            title: None,
+///                            len: ElementLength::Seconds(60),
+///                            genre: None,
+///                        },
+///                        PlaylistElement {
+///                            path: "Track 2.mp3".to_string(),
+///                            title: None,
+///                            len: ElementLength::Seconds(360),
+///                            genre: None,
+///                        },
+///                        PlaylistElement {
+///                            path: "http://127.0.0.1:8002/stream".to_string(),
+///                            title: None,
+///                            len: ElementLength::Unknown,
+///                            genre: None,
+///                        }]),
+///            PlaylistStats {
+///                count: 3,
+///                known_lengths: 2,
+///                unknown_lengths: 1,
+///                total_known: Duration::from_secs(420),
+///                has_urls: true,
+///                has_local: true,
+///            });
+/// ```
+pub fn stats(elements: &[PlaylistElement]) -> PlaylistStats {
+    let mut ret = PlaylistStats {
+        count: elements.len(),
+        known_lengths: 0,
+        unknown_lengths: 0,
+        total_known: Duration::from_secs(0),
+        has_urls: false,
+        has_local: false,
+    };
+
+    for elem in elements {
+        match elem.len {
+            ElementLength::Seconds(s) => {
+                ret.known_lengths += 1;
+                ret.total_known = ret.total_known.checked_add(Duration::from_secs(s)).unwrap_or(Duration::MAX);
+            }
+            ElementLength::Unknown => ret.unknown_lengths += 1,
+        }
+
+        if elem.is_url() {
+            ret.has_urls = true;
+        } else {
+            ret.has_local = true;
+        }
+    }
+
+    ret
+}
+
+/// Clean up a playlist in place, with the steps enabled in `opts`, in this order:
+///
+///   1. `trim_titles` trims leading and trailing whitespace from all titles, and removes titles that are empty afterwards,
+///   2. `drop_empty_paths` removes all elements whose path is empty or consists only of whitespace,
+///   3. `dedup_by_path` removes all elements whose path is byte-for-byte equal to that of an element before it, so the first
+///      occurrence stays.
+///
+/// The order of the remaining elements is preserved, and `write()` numbers them consecutively, so there's no separate
+/// renumbering step.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, NormalizeOptions, ElementLength};
+/// let mut elements = vec![PlaylistElement {
+///                             path: "Track 1.mp3".to_string(),
+///                             title: Some("  Unknown Artist - Track 1 ".to_string()),
+///                             len: ElementLength::Seconds(420),
+///                             genre: None,
+///                         },
+///                         PlaylistElement {
+///                             path: " ".to_string(),
+///                             title: None,
+///                             len: ElementLength::Unknown,
+///                             genre: None,
+///                         },
+///                         PlaylistElement {
+///                             path: "Track 1.mp3".to_string(),
+///                             title: None,
+///                             len: ElementLength::Unknown,
+///                             genre: None,
+///                         },
+///                         PlaylistElement {
+///                             path: "Track 2.mp3".to_string(),
+///                             title: Some("\t".to_string()),
+///                             len: ElementLength::Unknown,
+///                             genre: None,
+///                         }];
+/// pls::normalize(&mut elements, NormalizeOptions::default());
+/// assert_eq!(elements,
+///            vec![PlaylistElement {
+///                     path: "Track 1.mp3".to_string(),
+///                     title: Some("Unknown Artist - Track 1".to_string()),
+///                     len: ElementLength::Seconds(420),
+///                     genre: None,
+///                 },
+///                 PlaylistElement {
+///                     path: "Track 2.mp3".to_string(),
+///                     title: None,
+///                     len: ElementLength::Unknown,
+///                     genre: None,
+///                 }]);
+/// ```
+pub fn normalize(elements: &mut Vec<PlaylistElement>, opts: NormalizeOptions) {
+    if opts.trim_titles {
+        for elem in elements.iter_mut() {
+            elem.title = elem.title.take().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        }
+    }
+
+    if opts.drop_empty_paths {
+        elements.retain(|e| !e.path.trim().is_empty());
+    }
+
+    if opts.dedup_by_path {
+        let mut seen = HashSet::new();
+        elements.retain(|e| seen.insert(e.path.clone()));
+    }
+}
+
+/// Check whether both playlists have the same paths the same amount of times, regardless of order, titles, and lengths
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// assert!(pls::same_set(&[elem("Track 1.mp3"), elem("Track 2.mp3")], &[elem("Track 2.mp3"), elem("Track 1.mp3")]));
+/// assert!(!pls::same_set(&[elem("Track 1.mp3"), elem("Track 1.mp3")], &[elem("Track 1.mp3")]));
+/// ```
+pub fn same_set(a: &[PlaylistElement], b: &[PlaylistElement]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut a = paths(a);
+    let mut b = paths(b);
+    a.sort_unstable();
+    b.sort_unstable();
+    a == b
+}
+
+/// Check whether both playlists are equal, element by element, ignoring differences in how the paths are spelled
+///
+/// Paths are compared after:
+///
+///   * trimming leading and trailing whitespace,
+///   * for paths that aren't URLs (per `PlaylistElement::is_url()`), replacing `\` with `/`,
+///     and removing trailing `/`s that don't make up a root, like `/` or `C:/`.
+///
+/// So `C:\Music\Track 1.mp3` equals `C:/Music/Track 1.mp3`, and `/srv/Music/` equals `/srv/Music`,
+/// but case and `.`/`..` components are significant, and URLs compare as-is.
+/// Titles, lengths, and genres are compared exactly.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// assert!(pls::eq_normalized(&[elem("C:\\Music\\Track 1.mp3"), elem("\\\\Server\\Music\\")],
+///                            &[elem("C:/Music/Track 1.mp3"), elem("//Server/Music")]));
+/// assert!(!pls::eq_normalized(&[elem("C:\\Music\\Track 1.mp3")], &[elem("c:/music/track 1.mp3")]));
+/// ```
+pub fn eq_normalized(a: &[PlaylistElement], b: &[PlaylistElement]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).all(|(a, b)| a.title == b.title && a.len == b.len && a.genre == b.genre && comparable_path(&a.path) == comparable_path(&b.path))
+}
+
+/// Build a playlist out of paths and lengths, as from a media scanner, without titles
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(pls::from_pairs(vec![("Track 1.mp3".to_string(), ElementLength::Seconds(420)),
+///                                 ("Track 2.mp3".to_string(), ElementLength::Unknown)]),
+///            vec![PlaylistElement {
+///                     path: "Track 1.mp3".to_string(),
+///                     title: None,
+///                     len: ElementLength::Seconds(420),
+///                     genre: None,
+///                 },
+///                 PlaylistElement {
+///                     path: "Track 2.mp3".to_string(),
+///                     title: None,
+///                     len: ElementLength::Unknown,
+///                     genre: None,
+///                 }]);
+/// ```
+pub fn from_pairs<I: IntoIterator<Item = (String, ElementLength)>>(pairs: I) -> Vec<PlaylistElement> {
+    pairs.into_iter().map(PlaylistElement::from).collect()
+}
+
+/// Build a playlist out of paths, titles, and durations, as read from the files' tags by a tag reader like the `id3` crate
+///
+/// Each element is built by `PlaylistElement::from_parts()`.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::path::PathBuf;
+/// # use std::time::Duration;
+/// let tags = vec![(PathBuf::from("Track 1.mp3"), Some("Unknown Artist - Track 1".to_string()), Some(Duration::from_secs(420))),
+///                 (PathBuf::from("Track 2.mp3"), None, None)];
+/// assert_eq!(pls::from_tags(tags),
+///            vec![PlaylistElement {
+///                     path: "Track 1.mp3".to_string(),
+///                     title: Some("Unknown Artist - Track 1".to_string()),
+///                     len: ElementLength::Seconds(420),
+///                     genre: None,
+///                 },
+///                 PlaylistElement {
+///                     path: "Track 2.mp3".to_string(),
+///                     title: None,
+///                     len: ElementLength::Unknown,
+///                     genre: None,
+///                 }]);
+/// ```
+pub fn from_tags<I: IntoIterator<Item = (PathBuf, Option<String>, Option<Duration>)>>(tags: I) -> Vec<PlaylistElement> {
+    tags.into_iter().map(PlaylistElement::from).collect()
+}
+
+/// Move the element at the 0-based position `from` so that it ends up at position `to`, shifting the ones in between
+///
+/// Fails with the offending position, leaving the elements untouched, if either is out of bounds.
+///
+/// `write()` numbers the elements consecutively, so they get their new indices there; there's nothing to renumber.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("Track 1.mp3"), elem("Track 2.mp3"), elem("Track 3.mp3")];
+/// pls::move_entry(&mut elements, 0, 2).unwrap();
+/// assert_eq!(pls::paths(&elements), vec!["Track 2.mp3", "Track 3.mp3", "Track 1.mp3"]);
+/// assert_eq!(pls::move_entry(&mut elements, 1, 3), Err(3));
+/// ```
+pub fn move_entry(elements: &mut Vec<PlaylistElement>, from: usize, to: usize) -> Result<(), usize> {
+    for &i in &[from, to] {
+        if i >= elements.len() {
+            return Err(i);
+        }
+    }
+
+    let elem = elements.remove(from);
+    elements.insert(to, elem);
+    Ok(())
+}
+
+/// Swap the elements at the 0-based positions `a` and `b`
+///
+/// Fails with the offending position, leaving the elements untouched, if either is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("Track 1.mp3"), elem("Track 2.mp3"), elem("Track 3.mp3")];
+/// pls::swap_entries(&mut elements, 0, 2).unwrap();
+/// assert_eq!(pls::paths(&elements), vec!["Track 3.mp3", "Track 2.mp3", "Track 1.mp3"]);
+/// assert_eq!(pls::swap_entries(&mut elements, 3, 1), Err(3));
+/// ```
+pub fn swap_entries(elements: &mut [PlaylistElement], a: usize, b: usize) -> Result<(), usize> {
+    for &i in &[a, b] {
+        if i >= elements.len() {
+            return Err(i);
+        }
+    }
+
+    elements.swap(a, b);
+    Ok(())
+}
+
+/// Remove the elements matching `pred`, returning them, in order, and leaving the rest in order
+///
+/// `write()` numbers the elements consecutively, so the ones after a removed element get lower `File#` indices there.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("Track 1.mp3"), elem("http://127.0.0.1:8002/stream"), elem("Track 3.mp3")];
+/// let trash = pls::filter_out(&mut elements, |e| e.is_url());
+/// assert_eq!(pls::paths(&elements), vec!["Track 1.mp3", "Track 3.mp3"]);
+/// assert_eq!(pls::paths(&trash), vec!["http://127.0.0.1:8002/stream"]);
+/// ```
+pub fn filter_out<F: FnMut(&PlaylistElement) -> bool>(elements: &mut Vec<PlaylistElement>, mut pred: F) -> Vec<PlaylistElement> {
+    let (removed, kept) = elements.drain(..).partition(|e| pred(e));
+    *elements = kept;
+    removed
+}
+
+/// Get the paths of all elements, in order
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// assert_eq!(pls::paths(&[PlaylistElement {
+///                            path: "Track 1.mp3".to_string(),
+///                            title: None,
+///                            len: ElementLength::Unknown,
+///                            genre: None,
+///                        },
+///                        PlaylistElement {
+///                            path: "http://127.0.0.1:8002/stream".to_string(),
+///                            title: None,
+///                            len: ElementLength::Unknown,
+///                            genre: None,
+///                        }]),
+///            vec!["Track 1.mp3", "http://127.0.0.1:8002/stream"]);
+/// ```
+pub fn paths(elements: &[PlaylistElement]) -> Vec<&str> {
+    elements.iter().map(|e| e.path_str()).collect()
+}
+
+/// Get the paths of all elements that aren't URLs, in order
+///
+/// URLs are told apart from local paths with `PlaylistElement::is_url()`, so `file:` URLs are skipped too.
+///
+/// The paths are taken verbatim and not checked against the filesystem;
+/// relative ones are relative to wherever the playlist was read from.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::path::PathBuf;
+/// assert_eq!(pls::local_paths(&[PlaylistElement {
+///                                  path: "Track 1.mp3".to_string(),
+///                                  title: None,
+///                                  len: ElementLength::Unknown,
+///                                  genre: None,
+///                              },
+///                              PlaylistElement {
+///                                  path: "http://127.0.0.1:8002/stream".to_string(),
+///                                  title: None,
+///                                  len: ElementLength::Unknown,
+///                                  genre: None,
+///                              }]),
+///            vec![PathBuf::from("Track 1.mp3")]);
+/// ```
+pub fn local_paths(elements: &[PlaylistElement]) -> Vec<PathBuf> {
+    elements.iter().filter(|e| !e.is_url()).map(|e| PathBuf::from(&e.path)).collect()
+}
+
+/// Group elements by the directory they're in, for showing a playlist by folder
+///
+/// The directory of a path is everything before its last `/` (or `\`, for all paths), keeping the separator for a root,
+/// like `/` or `C:\`, and `C:` for drive-relative paths; it's `""` for paths without any directory.
+/// URLs (per `PlaylistElement::path_kind()`), including `file:` ones, are all grouped under `"<url>"`.
+///
+/// The elements in each group are in their original order.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let elements = [elem("S:\\Music\\Track 1.mp3"),
+///                 elem("http://127.0.0.1:8002/stream"),
+///                 elem("Music/Track 2.mp3"),
+///                 elem("S:\\Music\\Track 3.mp3")];
+/// let groups = pls::group_by_dir(&elements);
+/// assert_eq!(groups.keys().collect::<Vec<_>>(), ["<url>", "Music", "S:\\Music"]);
+/// assert_eq!(groups["S:\\Music"], [&elements[0], &elements[3]]);
+/// ```
+pub fn group_by_dir(elements: &[PlaylistElement]) -> BTreeMap<String, Vec<&PlaylistElement>> {
+    let mut groups = BTreeMap::new();
+    for elem in elements {
+        groups.entry(parent_dir(&elem.path).to_string()).or_insert_with(Vec::new).push(elem);
+    }
+    groups
+}
+
+/// Turn the paths of all elements that aren't URLs into absolute `file:` URLs, resolving relative ones against `base`
+///
+/// `base` should be the absolute path of the directory the playlist is in.
+/// Windows paths (per `PlaylistElement::path_kind()`) are absolute regardless of the platform, and get their `\`s turned into `/`s;
+/// a UNC path's server becomes the URL's host.
+///
+/// Spaces, non-ASCII characters, and anything else not allowed in a URL path are percent-encoded, as are `#`, `;`, and `?`.
+/// Elements with empty paths are left alone.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use std::path::Path;
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("Track 1.mp3"), elem(r"S:\Music\Café.mp3"), elem(r"\\Server\Music\Track 3.mp3"), elem("http://127.0.0.1:8002/stream")];
+/// pls::to_file_urls(&mut elements, Path::new("/srv/Music"));
+/// assert_eq!(pls::paths(&elements),
+///            vec!["file:///srv/Music/Track%201.mp3",
+///                 "file:///S:/Music/Caf%C3%A9.mp3",
+///                 "file://Server/Music/Track%203.mp3",
+///                 "http://127.0.0.1:8002/stream"]);
+/// ```
+pub fn to_file_urls(elements: &mut [PlaylistElement], base: &Path) {
+    for elem in elements.iter_mut().filter(|e| !e.is_url() && !e.path.is_empty()) {
+        let path = if elem.path_kind() == PathKind::Local && !elem.path.starts_with('/') {
+            base.join(&elem.path).to_string_lossy().into_owned()
+        } else {
+            elem.path.clone()
+        };
+        elem.path = file_url(&path);
+    }
+}
+
+/// Turn all `file:` URLs back into bare paths, undoing `to_file_urls()`
+///
+/// Percent-encoding is decoded; URLs with a host other than `localhost` become UNC paths, like `\\Server\Music\Track 1.mp3`.
+/// URLs that don't decode to valid UTF-8 are left alone, as are all other paths.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("file:///srv/Music/Track%201.mp3"),
+///                         elem("file:///S:/Music/Caf%C3%A9.mp3"),
+///                         elem("file://Server/Music/Track%203.mp3"),
+///                         elem("Track 4.mp3")];
+/// pls::to_bare_paths(&mut elements);
+/// assert_eq!(pls::paths(&elements), vec!["/srv/Music/Track 1.mp3", "S:/Music/Café.mp3", r"\\Server\Music\Track 3.mp3", "Track 4.mp3"]);
+/// ```
+pub fn to_bare_paths(elements: &mut [PlaylistElement]) {
+    for elem in elements.iter_mut().filter(|e| is_file_url(&e.path)) {
+        if let Some(path) = bare_path(&elem.path["file:".len()..]) {
+            elem.path = path;
+        }
+    }
+}
+
+/// Get the paths of all elements that are URLs, in order, skipping ones that don't parse
+///
+/// Requires the `url` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate url;
+/// # extern crate pls;
+/// # use pls::{PlaylistElement, ElementLength};
+/// # use url::Url;
+/// # fn main() {
+/// assert_eq!(pls::urls(&[PlaylistElement {
+///                           path: "Track 1.mp3".to_string(),
+///                           title: None,
+///                           len: ElementLength::Unknown,
+///                           genre: None,
+///                       },
+///                       PlaylistElement {
+///                           path: "http://127.0.0.1:8002/stream".to_string(),
+///                           title: None,
+///                           len: ElementLength::Unknown,
+///                           genre: None,
+///                       }]),
+///            vec![Url::parse("http://127.0.0.1:8002/stream").unwrap()]);
+/// # }
+/// ```
+#[cfg(feature = "url")]
+pub fn urls(elements: &[PlaylistElement]) -> Vec<Url> {
+    elements.iter().filter(|e| e.is_url()).filter_map(|e| Url::parse(&e.path).ok()).collect()
+}
+
+/// Fix up titles that were UTF-8 mis-decoded as Latin-1 or Windows-1252, like `CafÃ©` for `Café`
+///
+/// A title is only changed if it has non-ASCII characters, all of them Latin-1 or Windows-1252 characters,
+/// and encoding it back to those bytes yields valid UTF-8;
+/// genuine Latin-1 text practically never does, so everything else is left alone.
+///
+/// Requires the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength};
+/// # let elem = |t: &str| PlaylistElement { path: "Track 1.mp3".to_string(), title: Some(t.to_string()), len: ElementLength::Unknown, genre: None };
+/// let mut elements = vec![elem("Unknown Artist â€” CafÃ©"), elem("Unknown Artist — Café")];
+/// pls::repair_mojibake(&mut elements);
+/// assert_eq!(elements[0].title_str(), "Unknown Artist — Café");
+/// assert_eq!(elements[1].title_str(), "Unknown Artist — Café");
+/// ```
+#[cfg(feature = "encoding")]
+pub fn repair_mojibake(elements: &mut [PlaylistElement]) {
+    for title in elements.iter_mut().filter_map(|e| e.title.as_mut()) {
+        if let Some(repaired) = unmojibake(title) {
+            *title = repaired;
+        }
+    }
+}
+
+
+/// Get the directory of `path`, for `group_by_dir()`
+fn parent_dir(path: &str) -> &str {
+    let kind = path_kind(path);
+    if kind == PathKind::Url {
+        return "<url>";
+    }
+
+    match path.rfind(&['/', '\\'][..]) {
+        Some(i) if i == 0 || (kind == PathKind::Drive && i == 2) => &path[..i + 1],
+        Some(i) => &path[..i],
+        None if kind == PathKind::Drive => &path[..2],
+        None => "",
+    }
+}
+
+/// Spell `path` the way `eq_normalized()` compares it
+fn comparable_path(path: &str) -> String {
+    let path = path.trim();
+    let kind = path_kind(path);
+    if kind == PathKind::Url {
+        return path.to_string();
+    }
+
+    let path = path.replace('\\', "/");
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.len() != path.len() && (trimmed.is_empty() || (kind == PathKind::Drive && trimmed.len() == 2)) {
+        path[..trimmed.len() + 1].to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Undo a single round of mis-decoding UTF-8 as Latin-1 or Windows-1252, if `s` looks like it went through one
+#[cfg(feature = "encoding")]
+fn unmojibake(s: &str) -> Option<String> {
+    if s.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut buf = [0u8; 4];
+    for c in s.chars() {
+        if (c as u32) < 0x100 {
+            // Latin-1 maps bytes straight to code points, and Windows-1252 only differs by having characters in 0x80..0xA0
+            bytes.push(c as u8);
+        } else {
+            let (b, _, unmappable) = WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+            if unmappable {
+                return None;
+            }
+            bytes.extend_from_slice(&b);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+
+/// Build a `file:` URL out of a path
+fn file_url(path: &str) -> String {
+    let (prefix, path) = match path_kind(path) {
+        PathKind::Unc => ("file:", path.replace('\\', "/")),
+        PathKind::Drive => ("file:///", path.replace('\\', "/")),
+        _ if path.starts_with('/') => ("file://", path.to_string()),
+        _ => ("file:", path.to_string()),
+    };
+
+    let mut ret = prefix.to_string();
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/:@!$&'()*+,=".contains(&b) {
+            ret.push(b as char);
+        } else {
+            ret.push_str(&format!("%{:02X}", b));
+        }
+    }
+    ret
+}
+
+/// Turn what comes after `file:` into a bare path, or `None` if it's not valid UTF-8
+fn bare_path(url: &str) -> Option<String> {
+    let decode = |s: &str| String::from_utf8(percent_decode(s)).ok();
+
+    let hosted = match url.strip_prefix("//") {
+        Some(hosted) => hosted,
+        None => return decode(url),
+    };
+    let (host, path) = hosted.split_at(hosted.find('/').unwrap_or(hosted.len()));
+    let path = decode(path)?;
+    if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+        // file:///S:/Music is S:/Music
+        let b = path.as_bytes();
+        if b.len() >= 3 && b[0] == b'/' && b[1].is_ascii_alphabetic() && b[2] == b':' {
+            Some(path[1..].to_string())
+        } else {
+            Some(path)
+        }
+    } else {
+        Some(format!(r"\\{}{}", decode(host)?, path.replace('/', "\\")))
+    }
+}
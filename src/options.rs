@@ -0,0 +1,533 @@
+//! Knobs for tweaking parsing, writing, and normalisation
+
+
+/// Configuration for `parse_with()`, and the other parsing functions taking it
+///
+/// The default is what `parse()` and the other functions not taking options use, and is lenient:
+/// everything that can be read unambiguously is.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Accept entries with no `File#` key, as long as they have a `Title#` or `Length#` key, yielding an empty `path`
+    ///
+    /// Some streaming "now playing" lists only carry titles.
+    ///
+    /// Default: `false`
+    pub allow_missing_file: bool,
+    /// Read `Ref#` and `HREF#` keys as `File#` keys, as some broken ASX converters write
+    ///
+    /// If an entry has more than one of them, `File#` wins, then `Ref#`, then `HREF#`.
+    ///
+    /// Default: `false`
+    pub allow_ref_keys: bool,
+    /// Name of the section to read the playlist from instead of `playlist`, still matched case-insensitively
+    ///
+    /// Default: `None`
+    pub section_name: Option<String>,
+    /// Fail with `ParseError::ConflictingCountKeys` if more than one entry count key is present and they disagree
+    ///
+    /// Otherwise `NumberOfEntries` wins, then other capitalisations of it, like `numberofentries`,
+    /// then `NumberOfEvents`, in any capitalisation.
+    ///
+    /// Default: `false`
+    pub reject_conflicting_counts: bool,
+    /// Fail with `ParseError::InvalidVersion` if the `Version` key is present and not one of these
+    ///
+    /// With `None`, any version is accepted, though it still has to be an integer.
+    ///
+    /// Default: `Some(vec![2])`, the only version there is
+    pub allowed_versions: Option<Vec<u64>>,
+    /// Fail with `ParseError::MissingKey` if there's no `Version` key, which the format requires
+    ///
+    /// Otherwise it's only validated if present.
+    /// This is for checking playlists from a known producer strictly, not for reading ones from the wild.
+    ///
+    /// Default: `false`
+    pub require_version: bool,
+    /// Fail with `ParseError::MissingKey` if there's no entry count key, which the format requires
+    ///
+    /// Otherwise, without one, every entry that's present is read, as without `require_contiguous_indices`,
+    /// and `ParseIter::missing_indices()` reports the gaps below the highest one.
+    ///
+    /// Default: `true`
+    pub require_number_of_entries: bool,
+    /// Read `Length#` values too big to fit in a `u64` as `u64::MAX` seconds instead of failing with
+    /// `ParseError::LengthOverflow`
+    ///
+    /// Default: `false`
+    pub clamp_overflowing_lengths: bool,
+    /// Accept `Length#` values with a decimal fraction, like `180.5`, as some audio editors write,
+    /// rounding them to the nearest whole second, with halves rounded up (so `180.5` is `Seconds(181)`)
+    ///
+    /// Only plain `digits.digits` is accepted; there's no sign or exponent, and `-1.0` is still invalid.
+    ///
+    /// Default: `false`
+    pub round_fractional_lengths: bool,
+    /// Read entries whose path is an `rtsp://`, `mms://`, or `shout://` URL as having an `Unknown` length,
+    /// ignoring their `Length#` key, valid or not
+    ///
+    /// Those protocols are only used for live streams, so any length given for them is bogus.
+    ///
+    /// Default: `false`
+    pub ignore_live_stream_lengths: bool,
+    /// Accept `Length#` values in the `M:SS` or `H:MM:SS` format, like `3:40`, as `LengthFormat::Clock` writes,
+    /// saturating at `u64::MAX` seconds
+    ///
+    /// The minutes (with hours) and seconds have to be two digits, under 60.
+    ///
+    /// Default: `false`
+    pub allow_clock_lengths: bool,
+    /// Accept `Length#` values followed by an `s` or `sec` unit, matched case-insensitively, like `420s` or `420 SEC`,
+    /// as some nonstandard tools write
+    ///
+    /// The rest is read as if there were no unit, so `-1s` is still `Unknown`.
+    ///
+    /// Default: `false`
+    pub allow_length_units: bool,
+    /// Read any negative integer `Length#` value, like `-2` or `-100`, as `Unknown`, as some buggy tools write,
+    /// instead of only `-1`, as the format specifies
+    ///
+    /// Default: `false`
+    pub negative_length_is_unknown: bool,
+    /// Join lines ending in a `\` with the next line, dropping the `\` and the line break, for hand-wrapped long URLs
+    ///
+    /// This isn't part of the format, and a Windows path can legitimately end in a `\`, so it's opt-in.
+    /// `ParseError::line()` counts joined lines as one.
+    ///
+    /// Default: `false`
+    pub join_continuation_lines: bool,
+    /// Read keys before the first section header as if they were in the playlist section
+    ///
+    /// Some broken exporters put `Version=2` or `NumberOfEntries` there; keys in the section itself take precedence.
+    ///
+    /// Default: `false`
+    pub allow_keys_before_section: bool,
+    /// Ignore everything before the first `[playlist]` header (or the one named by `section_name`), matched case-insensitively
+    ///
+    /// This recovers playlists that misbehaving servers or proxies prefix with junk, like a stray HTTP status line.
+    /// The junk doesn't need to be valid UTF-8.
+    ///
+    /// Default: `false`
+    pub skip_to_section: bool,
+    /// Read entries `1` through the entry count, failing with `ParseError::MissingKey` on the first one that's missing
+    ///
+    /// Keys of entries past the entry count are ignored.
+    ///
+    /// Otherwise every entry whose `File#` key is present (or, with `allow_missing_file`, any of its keys) is read,
+    /// and they're ordered numerically by index, so `File2` comes before `File10`, and numbered consecutively.
+    /// The entry count still has to be valid (and present, with `require_number_of_entries`), but it doesn't limit which entries are read,
+    /// so an entry past it is included, and one that's missing below it is skipped, as reported by `ParseIter::missing_indices()`.
+    ///
+    /// For example, with `NumberOfEntries=5` and `File1`, `File2`, and `File4`, this fails with `MissingKey` for `File3` when set,
+    /// and otherwise yields the three entries, with 3 and 5 missing.
+    ///
+    /// Default: `true`
+    pub require_contiguous_indices: bool,
+    /// Collapse runs of consecutive entries with the same path into the first one of each run, like some radio feeds need
+    ///
+    /// Unlike `NormalizeOptions::dedup_by_path`, entries with the same path that aren't next to one another are all kept.
+    /// This applies to the functions that return all elements at once, not to `parse_iter_with()`, whose length is exact.
+    ///
+    /// Default: `false`
+    pub coalesce_adjacent_duplicates: bool,
+    /// Fail with `ParseError::TooLarge` if the input is longer than this many bytes, reading at most one byte past that
+    ///
+    /// This keeps untrusted sources, like network streams, from filling all memory.
+    ///
+    /// Default: `None`
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            allow_missing_file: false,
+            allow_ref_keys: false,
+            section_name: None,
+            reject_conflicting_counts: false,
+            allowed_versions: Some(vec![2]),
+            require_version: false,
+            require_number_of_entries: true,
+            clamp_overflowing_lengths: false,
+            round_fractional_lengths: false,
+            ignore_live_stream_lengths: false,
+            allow_clock_lengths: false,
+            allow_length_units: false,
+            negative_length_is_unknown: false,
+            join_continuation_lines: false,
+            allow_keys_before_section: false,
+            skip_to_section: false,
+            require_contiguous_indices: true,
+            coalesce_adjacent_duplicates: false,
+            max_bytes: None,
+        }
+    }
+}
+
+/// Chainable setters, as an alternative to struct update syntax
+///
+/// # Examples
+///
+/// ```
+/// # use pls::ParseOptions;
+/// assert_eq!(ParseOptions::default().allow_missing_file(true).section_name("radio"),
+///            ParseOptions {
+///                allow_missing_file: true,
+///                section_name: Some("radio".to_string()),
+///                ..ParseOptions::default()
+///            });
+/// ```
+impl ParseOptions {
+    /// Set `allow_missing_file`
+    pub fn allow_missing_file(mut self, on: bool) -> ParseOptions {
+        self.allow_missing_file = on;
+        self
+    }
+
+    /// Set `allow_ref_keys`
+    pub fn allow_ref_keys(mut self, on: bool) -> ParseOptions {
+        self.allow_ref_keys = on;
+        self
+    }
+
+    /// Set `section_name`
+    pub fn section_name<S: Into<String>>(mut self, name: S) -> ParseOptions {
+        self.section_name = Some(name.into());
+        self
+    }
+
+    /// Set `reject_conflicting_counts`
+    pub fn reject_conflicting_counts(mut self, on: bool) -> ParseOptions {
+        self.reject_conflicting_counts = on;
+        self
+    }
+
+    /// Set `allowed_versions`
+    pub fn allowed_versions(mut self, versions: Option<Vec<u64>>) -> ParseOptions {
+        self.allowed_versions = versions;
+        self
+    }
+
+    /// Set `require_version`
+    pub fn require_version(mut self, on: bool) -> ParseOptions {
+        self.require_version = on;
+        self
+    }
+
+    /// Set `require_number_of_entries`
+    pub fn require_number_of_entries(mut self, on: bool) -> ParseOptions {
+        self.require_number_of_entries = on;
+        self
+    }
+
+    /// Set `clamp_overflowing_lengths`
+    pub fn clamp_overflowing_lengths(mut self, on: bool) -> ParseOptions {
+        self.clamp_overflowing_lengths = on;
+        self
+    }
+
+    /// Set `round_fractional_lengths`
+    pub fn round_fractional_lengths(mut self, on: bool) -> ParseOptions {
+        self.round_fractional_lengths = on;
+        self
+    }
+
+    /// Set `ignore_live_stream_lengths`
+    pub fn ignore_live_stream_lengths(mut self, on: bool) -> ParseOptions {
+        self.ignore_live_stream_lengths = on;
+        self
+    }
+
+    /// Set `allow_clock_lengths`
+    pub fn allow_clock_lengths(mut self, on: bool) -> ParseOptions {
+        self.allow_clock_lengths = on;
+        self
+    }
+
+    /// Set `allow_length_units`
+    pub fn allow_length_units(mut self, on: bool) -> ParseOptions {
+        self.allow_length_units = on;
+        self
+    }
+
+    /// Set `negative_length_is_unknown`
+    pub fn negative_length_is_unknown(mut self, on: bool) -> ParseOptions {
+        self.negative_length_is_unknown = on;
+        self
+    }
+
+    /// Set `join_continuation_lines`
+    pub fn join_continuation_lines(mut self, on: bool) -> ParseOptions {
+        self.join_continuation_lines = on;
+        self
+    }
+
+    /// Set `allow_keys_before_section`
+    pub fn allow_keys_before_section(mut self, on: bool) -> ParseOptions {
+        self.allow_keys_before_section = on;
+        self
+    }
+
+    /// Set `skip_to_section`
+    pub fn skip_to_section(mut self, on: bool) -> ParseOptions {
+        self.skip_to_section = on;
+        self
+    }
+
+    /// Set `require_contiguous_indices`
+    pub fn require_contiguous_indices(mut self, on: bool) -> ParseOptions {
+        self.require_contiguous_indices = on;
+        self
+    }
+
+    /// Set `coalesce_adjacent_duplicates`
+    pub fn coalesce_adjacent_duplicates(mut self, on: bool) -> ParseOptions {
+        self.coalesce_adjacent_duplicates = on;
+        self
+    }
+
+    /// Set `max_bytes`
+    pub fn max_bytes(mut self, max: usize) -> ParseOptions {
+        self.max_bytes = Some(max);
+        self
+    }
+}
+
+/// Configuration for `write_with()`, and the other writing functions taking it
+///
+/// The default is what `write()` and the other functions not taking options use, and writes exactly what they always have.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Fail with `io::ErrorKind::InvalidInput`, without writing anything, when there are no elements to write
+    ///
+    /// Some players reject playlists with `NumberOfEntries=0`.
+    ///
+    /// Default: `false`
+    pub error_on_empty: bool,
+    /// Pad the keys of each entry with spaces before the `=`, so that the values line up, like `File1  =` and `Length1=`
+    ///
+    /// This is for playlists kept in source control or edited by hand;
+    /// it reads back the same, since keys are trimmed when parsing.
+    ///
+    /// Default: `false`
+    pub align_keys: bool,
+    /// How to write `Length#` values
+    ///
+    /// Default: `LengthFormat::Seconds`
+    pub length_format: LengthFormat,
+    /// Start the output with a UTF-8 byte order mark, which some Windows players need to not read the playlist as ANSI
+    ///
+    /// `parse()` ignores it.
+    ///
+    /// Default: `false`
+    pub byte_order_mark: bool,
+    /// Write a blank line after each entry, separating it from the next one, or from the entry count
+    ///
+    /// Some hardware players choke on blank lines; it reads back the same without them.
+    ///
+    /// Default: `true`
+    pub blank_line_between_entries: bool,
+    /// Write the `NumberOfEntries` key, which the format requires
+    ///
+    /// Without it, the output is technically non-conformant, but most players accept it, counting the `File#` keys instead;
+    /// `parse()` doesn't, but `parse_with()` does without `ParseOptions::require_number_of_entries`.
+    ///
+    /// Default: `true`
+    pub emit_number_of_entries: bool,
+    /// Write the trailing `Version=2` line
+    ///
+    /// It's optional when parsing, here and in most players, but some older ones choke on it.
+    ///
+    /// Default: `true`
+    pub emit_version: bool,
+    /// What to end every line with, including the last one
+    ///
+    /// Default: `LineEnding::Lf`
+    pub line_ending: LineEnding,
+    /// Index of the first entry, numbering the `File#`, `Title#`, `Length#`, and `Genre#` keys
+    ///
+    /// Some tools number from 0; `parse()` only reads playlists numbered from 1 back,
+    /// `parse_with()` reads any other start without `ParseOptions::require_contiguous_indices`, except 0, which is never read.
+    ///
+    /// Default: `1`
+    pub start_index: u64,
+    /// Order to write the keys of each entry in, if present
+    ///
+    /// Keys listed more than once are written at their first position,
+    /// keys not listed at all are written after the rest, in the default order.
+    ///
+    /// Default: `[EntryKey::File, EntryKey::Title, EntryKey::Length, EntryKey::Genre]`
+    pub key_order: [EntryKey; 4],
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            error_on_empty: false,
+            align_keys: false,
+            length_format: LengthFormat::Seconds,
+            byte_order_mark: false,
+            blank_line_between_entries: true,
+            emit_number_of_entries: true,
+            emit_version: true,
+            line_ending: LineEnding::Lf,
+            start_index: 1,
+            key_order: EntryKey::ALL,
+        }
+    }
+}
+
+/// Chainable setters, as an alternative to struct update syntax
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{LengthFormat, WriteOptions};
+/// assert_eq!(WriteOptions::default().align_keys(true).length_format(LengthFormat::Clock),
+///            WriteOptions {
+///                align_keys: true,
+///                length_format: LengthFormat::Clock,
+///                ..WriteOptions::default()
+///            });
+/// ```
+impl WriteOptions {
+    /// Set `error_on_empty`
+    pub fn error_on_empty(mut self, on: bool) -> WriteOptions {
+        self.error_on_empty = on;
+        self
+    }
+
+    /// Set `align_keys`
+    pub fn align_keys(mut self, on: bool) -> WriteOptions {
+        self.align_keys = on;
+        self
+    }
+
+    /// Set `length_format`
+    pub fn length_format(mut self, format: LengthFormat) -> WriteOptions {
+        self.length_format = format;
+        self
+    }
+
+    /// Set `byte_order_mark`
+    pub fn byte_order_mark(mut self, on: bool) -> WriteOptions {
+        self.byte_order_mark = on;
+        self
+    }
+
+    /// Set `blank_line_between_entries`
+    pub fn blank_line_between_entries(mut self, on: bool) -> WriteOptions {
+        self.blank_line_between_entries = on;
+        self
+    }
+
+    /// Set `emit_number_of_entries`
+    pub fn emit_number_of_entries(mut self, on: bool) -> WriteOptions {
+        self.emit_number_of_entries = on;
+        self
+    }
+
+    /// Set `emit_version`
+    pub fn emit_version(mut self, on: bool) -> WriteOptions {
+        self.emit_version = on;
+        self
+    }
+
+    /// Set `line_ending`
+    pub fn line_ending(mut self, ending: LineEnding) -> WriteOptions {
+        self.line_ending = ending;
+        self
+    }
+
+    /// Set `start_index`
+    pub fn start_index(mut self, idx: u64) -> WriteOptions {
+        self.start_index = idx;
+        self
+    }
+
+    /// Set `key_order`
+    pub fn key_order(mut self, order: [EntryKey; 4]) -> WriteOptions {
+        self.key_order = order;
+        self
+    }
+}
+
+/// Format of `Length#` values, for `WriteOptions::length_format`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum LengthFormat {
+    /// Whole seconds, like `220`, as the format specifies
+    #[default]
+    Seconds,
+    /// `M:SS` under an hour, like `3:40`, otherwise `H:MM:SS`, like `1:02:03`
+    ///
+    /// Some non-conformant players display this, but most can't read it; `ParseOptions::allow_clock_lengths` reads it back.
+    Clock,
+}
+
+/// Line terminator, for `WriteOptions::line_ending`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`, as on everything but Windows
+    #[default]
+    Lf,
+    /// `\r\n`, which some Windows players, like Windows Media Player, handle better
+    CrLf,
+}
+
+impl LineEnding {
+    /// Get the terminator itself
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pls::LineEnding;
+    /// assert_eq!(LineEnding::Lf.as_str(), "\n");
+    /// assert_eq!(LineEnding::CrLf.as_str(), "\r\n");
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Key of a playlist entry, for `WriteOptions::key_order`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum EntryKey {
+    /// `File#`
+    File,
+    /// `Title#`
+    Title,
+    /// `Length#`
+    Length,
+    /// `Genre#`
+    Genre,
+}
+
+impl EntryKey {
+    /// All keys, in the order they're written by default
+    pub const ALL: [EntryKey; 4] = [EntryKey::File, EntryKey::Title, EntryKey::Length, EntryKey::Genre];
+}
+
+/// Configuration for `normalize()`, toggling each step
+///
+/// The default enables all steps.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Trim leading and trailing whitespace from titles, replacing titles that end up empty with `None`
+    pub trim_titles: bool,
+    /// Remove elements whose path is empty or only whitespace
+    pub drop_empty_paths: bool,
+    /// Remove elements whose path is exactly the same as that of an earlier element
+    pub dedup_by_path: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> NormalizeOptions {
+        NormalizeOptions {
+            trim_titles: true,
+            drop_empty_paths: true,
+            dedup_by_path: true,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Writing playlists one element at a time
+
+
+use super::{PlaylistElementGen, WriteOptions, write_header_with, write_entry_with, write_footer_with, entry_index};
+use std::io::{self, Write};
+
+
+/// Streaming playlist writer, for when the elements aren't all available up front
+///
+/// The header is written with the first element (or by `finish()`), each element as it's pushed,
+/// and `finish()` writes the entry count, so the output is only a valid playlist after that.
+///
+/// # Errors in `extend()`
+///
+/// `Extend` can't return errors, so it holds on to the first one instead, skipping all elements after it,
+/// and `finish()` returns it, without writing the footer.
+/// Always call `finish()`: dropping the writer loses whatever error was held.
+///
+/// # Examples
+///
+/// ```
+/// # use pls::{PlaylistElement, ElementLength, Writer};
+/// # let elem = |p: &str| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None };
+/// let mut writer = Writer::new(Vec::new());
+/// writer.push(&elem("Track 1.mp3")).unwrap();
+/// writer.extend([elem("Track 2.mp3"), elem("Track 3.mp3")].iter());
+/// assert_eq!(writer.count(), 3);
+///
+/// assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(),
+///            "[playlist]\n\
+///             File1=Track 1.mp3\n\
+///             \n\
+///             File2=Track 2.mp3\n\
+///             \n\
+///             File3=Track 3.mp3\n\
+///             \n\
+///             NumberOfEntries=3\n\
+///             Version=2\n");
+/// ```
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    out: W,
+    opts: WriteOptions,
+    count: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Start writing a playlist to `out`, like `write()` does
+    pub fn new(out: W) -> Writer<W> {
+        Writer::with_options(out, WriteOptions::default())
+    }
+
+    /// Start writing a playlist to `out`, like `write_with()` does
+    ///
+    /// With `error_on_empty`, `finish()` fails if no elements were written, and nothing is written at all.
+    pub fn with_options(out: W, opts: WriteOptions) -> Writer<W> {
+        Writer {
+            out,
+            opts,
+            count: 0,
+            error: None,
+        }
+    }
+
+    /// Write the next element
+    ///
+    /// After an error, the output is incomplete, and continuing is unlikely to yield a valid playlist.
+    pub fn push<S: AsRef<str>>(&mut self, elem: &PlaylistElementGen<S>) -> io::Result<()> {
+        if self.count == 0 {
+            write_header_with(&self.opts, &mut self.out)?;
+        }
+
+        write_entry_with(entry_index(self.count, &self.opts)?, elem, &self.opts, &mut self.out)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// How many elements have been written so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Finish the playlist, returning the underlying writer, or the error held by `extend()`
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        if self.count == 0 {
+            if self.opts.error_on_empty {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "refusing to write an empty playlist"));
+            }
+            write_header_with(&self.opts, &mut self.out)?;
+        }
+        write_footer_with(self.count, &self.opts, &mut self.out)?;
+        Ok(self.out)
+    }
+}
+
+impl<'e, S: AsRef<str> + 'e, W: Write> Extend<&'e PlaylistElementGen<S>> for Writer<W> {
+    fn extend<I: IntoIterator<Item = &'e PlaylistElementGen<S>>>(&mut self, iter: I) {
+        if self.error.is_some() {
+            return;
+        }
+
+        for elem in iter {
+            if let Err(err) = self.push(elem) {
+                self.error = Some(err);
+                return;
+            }
+        }
+    }
+}
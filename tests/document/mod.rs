@@ -0,0 +1,317 @@
+use pls::{Document, PlaylistElement, ElementLength, ParseError};
+
+
+static MESSY: &str = "; Exported by Unknown Player\r\n\
+                      [Playlist]\r\n\
+                      File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                      ; Bought on vinyl\r\n\
+                      Length1=218\r\n\
+                      \r\n\
+                      \r\n\
+                      Title2=A-F-R-O - Animal Kingdom\r\n\
+                      File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                      \r\n\
+                      Version=2\r\n\
+                      NumberOfEntries : 2\r\n\
+                      \r\n\
+                      [Unrelated]\r\n\
+                      File1=Not an entry";
+
+fn write(doc: &Document) -> String {
+    let mut buf = Vec::new();
+    doc.write(&mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+fn element(path: &str) -> PlaylistElement {
+    PlaylistElement {
+        path: path.to_string(),
+        title: None,
+        len: ElementLength::Unknown,
+        genre: None,
+    }
+}
+
+
+#[test]
+fn untouched() {
+    let doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    assert_eq!(doc.entries, pls::parse(&mut MESSY.as_bytes()).unwrap());
+    assert_eq!(write(&doc), MESSY);
+}
+
+#[test]
+fn changed() {
+    let mut doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    doc.entries[1].len = ElementLength::Seconds(124);
+
+    assert_eq!(write(&doc),
+               "; Exported by Unknown Player\r\n\
+                [Playlist]\r\n\
+                File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                ; Bought on vinyl\r\n\
+                Length1=218\r\n\
+                \r\n\
+                \r\n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                Title2=A-F-R-O - Animal Kingdom\r\n\
+                Length2=124\r\n\
+                \r\n\
+                Version=2\r\n\
+                NumberOfEntries : 2\r\n\
+                \r\n\
+                [Unrelated]\r\n\
+                File1=Not an entry");
+}
+
+#[test]
+fn appended() {
+    let mut doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    doc.entries.push(element("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3"));
+
+    let written = write(&doc);
+    assert_eq!(written,
+               "; Exported by Unknown Player\r\n\
+                [Playlist]\r\n\
+                File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                ; Bought on vinyl\r\n\
+                Length1=218\r\n\
+                \r\n\
+                \r\n\
+                Title2=A-F-R-O - Animal Kingdom\r\n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                \r\n\
+                File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\r\n\
+                \r\n\
+                Version=2\r\n\
+                NumberOfEntries=3\r\n\
+                \r\n\
+                [Unrelated]\r\n\
+                File1=Not an entry");
+    assert_eq!(pls::parse(&mut written.as_bytes()).unwrap(), doc.entries);
+}
+
+#[test]
+fn appended_count_capitalisation() {
+    let mut doc = Document::parse(&mut MESSY.replace("NumberOfEntries : 2", "NUMBEROFENTRIES : 2").as_bytes()).unwrap();
+    doc.entries.push(element("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3"));
+
+    let written = write(&doc);
+    assert!(written.contains("\r\nVersion=2\r\nNUMBEROFENTRIES=3\r\n"), "{}", written);
+    assert_eq!(pls::parse(&mut written.as_bytes()).unwrap(), doc.entries);
+}
+
+#[test]
+fn removed() {
+    let mut doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    doc.entries.remove(0);
+
+    let written = write(&doc);
+    assert_eq!(written,
+               "; Exported by Unknown Player\r\n\
+                [Playlist]\r\n\
+                File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                Title1=A-F-R-O - Animal Kingdom\r\n\
+                ; Bought on vinyl\r\n\
+                \r\n\
+                \r\n\
+                \r\n\
+                Version=2\r\n\
+                NumberOfEntries=1\r\n\
+                \r\n\
+                [Unrelated]\r\n\
+                File1=Not an entry");
+    assert_eq!(pls::parse(&mut written.as_bytes()).unwrap(), doc.entries);
+}
+
+#[test]
+fn appended_to_empty() {
+    let mut doc = Document::parse(&mut &b"[playlist]\n\
+                                          NumberOfEntries=0"[..])
+        .unwrap();
+    doc.entries.push(element("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3"));
+
+    assert_eq!(write(&doc),
+               "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                \n\
+                NumberOfEntries=1\n");
+}
+
+#[test]
+fn appended_at_end() {
+    let mut doc = Document::parse(&mut &b"[playlist]\n\
+                                          NumberOfEntries=1\n\
+                                          File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3"[..])
+        .unwrap();
+    doc.entries.push(element("S:/M J U Z I K/pobrany/Stronger.mp3"));
+
+    assert_eq!(write(&doc),
+               "[playlist]\n\
+                NumberOfEntries=2\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                \n\
+                File2=S:/M J U Z I K/pobrany/Stronger.mp3\n");
+}
+
+#[test]
+fn invalid() {
+    assert_eq!(Document::parse(&mut &b"[playlist]\n"[..]),
+               Err(ParseError::MissingKey {
+                   key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+                   entry: None,
+               }));
+}
+
+#[test]
+fn comments_parsed() {
+    let doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    assert_eq!(doc.comments,
+               vec![(None, "Exported by Unknown Player".to_string()), (Some(0), "Bought on vinyl".to_string())]);
+
+    let doc = Document::parse(&mut &b"# Exported by Unknown Player\n\
+                                      [playlist]\n\
+                                      ;\n\
+                                      File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                      \n\
+                                      ;Next up:\n\
+                                      File2=S:/M J U Z I K/pobrany/Stronger.mp3\n\
+                                      ; That's all\n\
+                                      NumberOfEntries=2\n\
+                                      [Unrelated]\n\
+                                      ; Not ours\n"[..])
+        .unwrap();
+    assert_eq!(doc.comments,
+               vec![(None, "Exported by Unknown Player".to_string()),
+                    (Some(0), "".to_string()),
+                    (Some(1), "Next up:".to_string()),
+                    (Some(2), "That's all".to_string())]);
+}
+
+#[test]
+fn comments_edited() {
+    let mut doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    doc.comments.remove(1);
+    doc.comments.push((Some(1), "A-F-R-O - Tales From The Basement".to_string()));
+    doc.comments.push((Some(2), "Before the new one".to_string()));
+    doc.comments.push((Some(420), "The end".to_string()));
+    doc.comments.push((None, "".to_string()));
+    doc.entries.push(element("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3"));
+
+    let written = write(&doc);
+    assert_eq!(written,
+               "; Exported by Unknown Player\r\n\
+                ;\r\n\
+                [Playlist]\r\n\
+                File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                Length1=218\r\n\
+                \r\n\
+                \r\n\
+                ; A-F-R-O - Tales From The Basement\r\n\
+                Title2=A-F-R-O - Animal Kingdom\r\n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                \r\n\
+                ; Before the new one\r\n\
+                File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\r\n\
+                \r\n\
+                Version=2\r\n\
+                NumberOfEntries=3\r\n\
+                \r\n\
+                [Unrelated]\r\n\
+                File1=Not an entry\r\n\
+                ; The end\r\n");
+
+    let reparsed = Document::parse(&mut written.as_bytes()).unwrap();
+    assert_eq!(reparsed.entries, doc.entries);
+    assert_eq!(reparsed.comments,
+               vec![(None, "Exported by Unknown Player".to_string()),
+                    (None, "".to_string()),
+                    (Some(1), "A-F-R-O - Tales From The Basement".to_string()),
+                    (Some(2), "Before the new one".to_string())]);
+}
+
+#[test]
+fn length_text_kept() {
+    let data = "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Length1=0218\n\
+                \n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                Length2 = 007\n\
+                \n\
+                NumberOfEntries=2\n";
+    let mut doc = Document::parse(&mut data.as_bytes()).unwrap();
+    assert_eq!(write(&doc), data);
+
+    doc.entries[0].title = Some("A-F-R-O & NGHTMRE - Stronger".to_string());
+    doc.entries[1].len = ElementLength::Seconds(124);
+    assert_eq!(write(&doc),
+               "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Title1=A-F-R-O & NGHTMRE - Stronger\n\
+                Length1=0218\n\
+                \n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                Length2=124\n\
+                \n\
+                NumberOfEntries=2\n");
+}
+
+#[test]
+fn length_text_kept_in_own_line_only() {
+    let data = "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Length1=0420\n\
+                NumberOfEntries=1\n";
+    let mut doc = Document::parse(&mut data.as_bytes()).unwrap();
+    doc.entries[0].title = Some("xLength1=420".to_string());
+    doc.entries[0].genre = Some("Length1=420".to_string());
+    assert_eq!(write(&doc),
+               "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Title1=xLength1=420\n\
+                Length1=0420\n\
+                Genre1=Length1=420\n\
+                NumberOfEntries=1\n");
+}
+
+#[test]
+fn brackets() {
+    let mut doc = Document::parse(&mut MESSY.as_bytes()).unwrap();
+    doc.entries[0].title = Some("[Live] A-F-R-O & NGHTMRE - Stronger".to_string());
+    doc.entries.push(PlaylistElement { title: Some("[playlist]".to_string()), ..element("[Unrelated]") });
+    doc.comments.push((Some(2), "Live set\n[playlist]\r\nNumberOfEntries=0".to_string()));
+
+    let written = write(&doc);
+    assert!(written.contains("; Live set\r\n; [playlist]\r\n; NumberOfEntries=0\r\n"), "{}", written);
+    let reparsed = Document::parse(&mut written.as_bytes()).unwrap();
+    assert_eq!(reparsed.entries, doc.entries);
+    assert_eq!(write(&reparsed), written);
+}
+
+#[test]
+fn byte_order_mark() {
+    let data = format!("\u{FEFF}{}", MESSY);
+    let mut doc = Document::parse(&mut data.as_bytes()).unwrap();
+    assert_eq!(write(&doc), data);
+
+    doc.entries.push(element("Stronger.mp3"));
+    let written = write(&doc);
+    assert!(written.starts_with("\u{FEFF}; Exported by Unknown Player\r\n[Playlist]\r\n"), "{}", written);
+    assert_eq!(Document::parse(&mut written.as_bytes()).unwrap().entries, doc.entries);
+}
+
+#[test]
+fn header_trailer() {
+    let data = MESSY.replace("[Playlist]\r\n", "[ Playlist ] ; exported 2017-06-12\r\n");
+    let mut doc = Document::parse(&mut data.as_bytes()).unwrap();
+    assert_eq!(doc.comments, vec![(None, "Exported by Unknown Player".to_string()), (Some(0), "Bought on vinyl".to_string())]);
+    assert_eq!(write(&doc), data);
+
+    doc.entries[0].title = Some("A-F-R-O & NGHTMRE - Stronger".to_string());
+    doc.comments.push((Some(1), "Remastered".to_string()));
+    let written = write(&doc);
+    assert!(written.starts_with("; Exported by Unknown Player\r\n[ Playlist ] ; exported 2017-06-12\r\nFile1=S:/"), "{}", written);
+    assert!(written.contains("\r\n; Remastered\r\nTitle2="), "{}", written);
+    assert_eq!(Document::parse(&mut written.as_bytes()).unwrap().entries, doc.entries);
+}
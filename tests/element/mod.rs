@@ -0,0 +1,222 @@
+use pls::{PlaylistElement, ElementLength, MediaKind, PathKind};
+use std::convert::TryFrom;
+
+
+fn element(path: &str) -> PlaylistElement {
+    PlaylistElement {
+        path: path.to_string(),
+        title: None,
+        len: ElementLength::Unknown,
+        genre: None,
+    }
+}
+
+
+#[test]
+fn path_kind_url() {
+    for path in &["http://127.0.0.1:8002/%D0%BC%D1%83%D0%B7%D1%8B%D0%BA%D0%B0/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3",
+                  "HTTPS://example.com/stream",
+                  "svn+ssh://example.com/music/Stronger.mp3",
+                  "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3",
+                  "file://Server/Music/Stronger.mp3",
+                  "file:pobrany/Stronger.mp3",
+                  "FILE:Stronger.mp3"] {
+        assert_eq!(element(path).path_kind(), PathKind::Url, "{}", path);
+        assert!(element(path).is_url(), "{}", path);
+    }
+}
+
+#[test]
+fn path_kind_unc() {
+    for path in &[r"\\Server\Music\A-F-R-O & NGHTMRE - Stronger.mp3", r"\\?\S:\M J U Z I K\pobrany\Stronger.mp3", r"\\Server\http://x"] {
+        assert_eq!(element(path).path_kind(), PathKind::Unc, "{}", path);
+        assert!(!element(path).is_url(), "{}", path);
+    }
+}
+
+#[test]
+fn path_kind_drive() {
+    for path in &[r"S:\M J U Z I K\pobrany\A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "s://M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "S:pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "C:"] {
+        assert_eq!(element(path).path_kind(), PathKind::Drive, "{}", path);
+        assert!(!element(path).is_url(), "{}", path);
+    }
+}
+
+#[test]
+fn path_kind_local() {
+    for path in &["/srv/music/A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                  "://nothing",
+                  "1http://not.a.scheme",
+                  "a b://not.a.scheme",
+                  "pobrany/file:Stronger.mp3",
+                  ""] {
+        assert_eq!(element(path).path_kind(), PathKind::Local, "{}", path);
+        assert!(!element(path).is_url(), "{}", path);
+    }
+}
+
+#[test]
+fn length_from_str() {
+    assert_eq!("0".parse(), Ok(ElementLength::Seconds(0)));
+    assert_eq!("124".parse(), Ok(ElementLength::Seconds(124)));
+    assert_eq!("-1".parse(), Ok(ElementLength::Unknown));
+    assert_eq!(ElementLength::try_from("79"), Ok(ElementLength::Seconds(79)));
+    assert_eq!(ElementLength::try_from("-1"), Ok(ElementLength::Unknown));
+}
+
+#[test]
+fn length_from_str_invalid() {
+    for s in &["", "-2", "1.5", "Abolish the Burgeoisie!", "99999999999999999999999"] {
+        assert_eq!(s.parse::<ElementLength>(), Err(s.parse::<u64>().unwrap_err()));
+        assert_eq!(ElementLength::try_from(*s), Err(s.parse::<u64>().unwrap_err()));
+    }
+}
+
+#[test]
+fn length_accessors() {
+    for &len in &[ElementLength::Seconds(0), ElementLength::Seconds(124), ElementLength::Seconds(u64::MAX)] {
+        assert!(len.is_known());
+        assert!(!len.is_unknown());
+        assert_eq!(Some(len.unwrap_or(79)), len.seconds());
+    }
+
+    assert!(!ElementLength::Unknown.is_known());
+    assert!(ElementLength::Unknown.is_unknown());
+    assert_eq!(ElementLength::Unknown.seconds(), None);
+    assert_eq!(ElementLength::Unknown.unwrap_or(79), 79);
+    assert_eq!("-1".parse::<ElementLength>().map(|l| l.seconds()), Ok(None));
+}
+
+#[test]
+fn length_option_round_trip() {
+    for &secs in &[Some(0), Some(124), Some(u64::MAX), None] {
+        let len = ElementLength::from(secs);
+        assert_eq!(len.seconds(), secs);
+        assert_eq!(Option::<u64>::from(len), secs);
+    }
+
+    for &len in &[ElementLength::Seconds(0), ElementLength::Seconds(79), ElementLength::Unknown] {
+        let secs: Option<u64> = len.into();
+        assert_eq!(ElementLength::from(secs), len);
+    }
+    assert_eq!(ElementLength::from("-1".parse::<ElementLength>().unwrap().seconds()), ElementLength::Unknown);
+}
+
+#[test]
+fn mutators() {
+    let mut elem = element("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3");
+
+    elem.set_title(Some("A-F-R-O & NGHTMRE — Stronger".to_string()));
+    elem.set_length_secs(218);
+    assert_eq!(elem.title, Some("A-F-R-O & NGHTMRE — Stronger".to_string()));
+    assert_eq!(elem.len, ElementLength::Seconds(218));
+
+    elem.set_title(None);
+    elem.clear_length();
+    assert_eq!(elem, element("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3"));
+}
+
+#[test]
+fn decoded_path() {
+    for &(path, decoded) in &[("http://127.0.0.1:8002/%D0%BC%D1%83%D0%B7%D1%8B%D0%BA%D0%B0/Z%20p%C5%82yt/03%20%23CODE%20829.mp3",
+                               "http://127.0.0.1:8002/музыка/Z płyt/03 #CODE 829.mp3"),
+                              ("file:///S:/M%20J%20U%20Z%20I%20K/100%.mp3", "file:///S:/M J U Z I K/100%.mp3"),
+                              ("http://example.com/%G1%2", "http://example.com/%G1%2"),
+                              ("http://example.com/%7e%7E", "http://example.com/~~"),
+                              ("S:/M J U Z I K/pobrany/A-F-R-O%20&%20NGHTMRE.mp3", "S:/M J U Z I K/pobrany/A-F-R-O%20&%20NGHTMRE.mp3")] {
+        assert_eq!(element(path).decoded_path(), decoded, "{}", path);
+        assert_eq!(element(path).decoded_path_lossy(), decoded, "{}", path);
+    }
+}
+
+#[test]
+fn decoded_path_invalid_utf8() {
+    let elem = element("http://127.0.0.1:8002/Stronger%FF%FE.mp3");
+    assert_eq!(elem.decoded_path(), "http://127.0.0.1:8002/Stronger%FF%FE.mp3");
+    assert_eq!(elem.decoded_path_lossy(), "http://127.0.0.1:8002/Stronger\u{FFFD}\u{FFFD}.mp3");
+}
+
+#[test]
+fn is_stream() {
+    for &(path, len, stream) in &[("http://127.0.0.1:8002/stream", ElementLength::Unknown, true),
+                                  ("HTTPS://example.com/stream", ElementLength::Unknown, true),
+                                  ("mms://example.com/stream", ElementLength::Unknown, true),
+                                  ("mms://example.com/stream", ElementLength::Seconds(218), true),
+                                  ("RTSP://127.0.0.1:8554/stream", ElementLength::Seconds(218), true),
+                                  ("shout://127.0.0.1:8000/stream", ElementLength::Seconds(0), true),
+                                  ("rtsp:stream", ElementLength::Seconds(218), false),
+                                  ("http://127.0.0.1:8002/A-F-R-O%20-%20Stronger.mp3", ElementLength::Seconds(218), false),
+                                  ("file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3", ElementLength::Unknown, false),
+                                  ("FILE:Stronger.mp3", ElementLength::Unknown, false),
+                                  (r"\\Server\Music\A-F-R-O & NGHTMRE - Stronger.mp3", ElementLength::Unknown, false),
+                                  ("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", ElementLength::Unknown, false)] {
+        let mut elem = element(path);
+        elem.len = len;
+        assert_eq!(elem.is_stream(), stream, "{}", path);
+    }
+}
+
+#[test]
+fn matches() {
+    let elem = PlaylistElement {
+        title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+        ..element(r"S:\M J U Z I K\pobrany\Stronger (Original Mix).MP3")
+    };
+    for &(query, case_insensitive, expected) in &[("Stronger", false, true),
+                                                  ("stronger", false, false),
+                                                  ("stronger", true, true),
+                                                  ("original mix).mp3", true, true),
+                                                  ("NGHTMRE - S", false, true),
+                                                  ("pobrany", true, false),
+                                                  ("M J U Z I K", false, false),
+                                                  ("", false, true),
+                                                  ("Stronger (Original Mix).MP3A-F-R-O", false, false)] {
+        assert_eq!(elem.matches(query, case_insensitive), expected, "{:?} {}", query, case_insensitive);
+    }
+
+    assert!(!element("http://127.0.0.1:8002/Музыка/stream").matches("музыка", true));
+    assert!(element("http://127.0.0.1:8002/stream?Музыка").matches("музыка", true));
+    assert!(element("Stronger.mp3").matches("", true));
+    assert!(!element("").matches("Stronger", true));
+}
+
+#[test]
+fn extension() {
+    for &(path, ext) in &[("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("mp3")),
+                          (r"S:\M J U Z I K\pobrany\Stronger.FLAC", Some("flac")),
+                          ("S:Stronger.M4a", Some("m4a")),
+                          ("http://127.0.0.1:8002/music/Stronger.mp3?sid=1&type=.pls", Some("mp3")),
+                          ("http://127.0.0.1:8002/music/Stronger.ogg#t=10.5", Some("ogg")),
+                          ("file:///srv/music/Stronger.tar.gz", Some("gz")),
+                          ("http://example.com", None),
+                          ("http://example.com/", None),
+                          ("http://127.0.0.1:8002/stream", None),
+                          ("/srv/music.d/Stronger", None),
+                          ("/srv/music/.mp3", None),
+                          ("/srv/music/Stronger.", None),
+                          ("Stronger.mp3?", Some("mp3?")),
+                          ("", None)] {
+        assert_eq!(element(path).extension().as_deref(), ext, "{}", path);
+    }
+}
+
+#[test]
+fn media_kind() {
+    for &(path, kind) in &[("Stronger.mp3", MediaKind::Audio),
+                           ("Stronger.Opus", MediaKind::Audio),
+                           ("http://127.0.0.1:8002/Stronger.m4a?token=abc", MediaKind::Audio),
+                           ("Stronger.mkv", MediaKind::Video),
+                           ("https://example.com/Stronger.MP4?t=10", MediaKind::Video),
+                           ("http://127.0.0.1:8002/listen.m3u8?sid=1", MediaKind::Playlist),
+                           ("Stronger.pls", MediaKind::Playlist),
+                           ("Stronger.txt", MediaKind::Unknown),
+                           ("http://127.0.0.1:8002/stream", MediaKind::Unknown),
+                           ("Stronger", MediaKind::Unknown)] {
+        assert_eq!(element(path).media_kind(), kind, "{}", path);
+    }
+}
@@ -1,7 +1,18 @@
 extern crate pls;
 extern crate ini;
+#[cfg(feature = "url")]
+extern crate url;
+#[cfg(feature = "encoding")]
+extern crate encoding_rs;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
 
+mod document;
+mod element;
+mod ops;
 mod parse;
+mod write;
+mod m3u;
 
 use pls::{PlaylistElement, ElementLength};
 
@@ -13,16 +24,19 @@ fn write() {
                                 path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
                                 title: None,
                                 len: ElementLength::Unknown,
+                                genre: None,
                             },
                             PlaylistElement {
                                 path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
                                 title: None,
                                 len: ElementLength::Seconds(79),
+                                genre: None,
                             },
                             PlaylistElement {
                                 path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
                                 title: Some("A-F-R-O - Animal Kingdom".to_string()),
                                 len: ElementLength::Seconds(124),
+                                genre: None,
                             },
                             PlaylistElement {
                                 path: "http://127.0.0.1:\
@@ -31,6 +45,7 @@ fn write() {
                                     .to_string(),
                                 title: Some("A-F-R-O - CODE 829".to_string()),
                                 len: ElementLength::Unknown,
+                                genre: None,
                             }],
                           &mut buf)
                    .ok(),
@@ -0,0 +1,163 @@
+use pls::{PlaylistElement, ElementLength, transcode_m3u_to_pls, write_m3u};
+use std::io;
+
+
+fn transcode(m3u: &[u8]) -> io::Result<Vec<PlaylistElement>> {
+    let mut buf = Vec::new();
+    transcode_m3u_to_pls(&mut &m3u[..], &mut buf)?;
+    assert!(pls::is_valid_pls(&buf), "{}", String::from_utf8_lossy(&buf));
+    Ok(pls::parse(&mut &buf[..]).unwrap())
+}
+
+
+#[test]
+fn extended() {
+    assert_eq!(transcode(b"\xEF\xBB\xBF#EXTM3U\r\n\
+                           #EXTINF:230,A-F-R-O & NGHTMRE - Stronger\r\n\
+                           S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                           \r\n\
+                           # Bought on vinyl\r\n\
+                           #EXTINF:79.5 , A-F-R-O - Activated Trap Locks \r\n\
+                           #EXTGENRE:Trap\r\n\
+                           S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\r\n\
+                           #EXTINF:-1 tvg-id=\"radio.1\" group-title=\"Radio, Talk\",Unknown Radio\r\n\
+                           http://127.0.0.1:8002/stream\r\n\
+                             http://127.0.0.1:8003/stream  \r\n")
+                   .unwrap(),
+               vec![PlaylistElement {
+                        path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                        title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                        len: ElementLength::Seconds(230),
+                        genre: None,
+                    },
+                    PlaylistElement {
+                        path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                        title: Some("A-F-R-O - Activated Trap Locks".to_string()),
+                        len: ElementLength::Seconds(80),
+                        genre: Some("Trap".to_string()),
+                    },
+                    PlaylistElement {
+                        path: "http://127.0.0.1:8002/stream".to_string(),
+                        title: Some("Unknown Radio".to_string()),
+                        len: ElementLength::Unknown,
+                        genre: None,
+                    },
+                    PlaylistElement {
+                        path: "http://127.0.0.1:8003/stream".to_string(),
+                        title: None,
+                        len: ElementLength::Unknown,
+                        genre: None,
+                    }]);
+}
+
+#[test]
+fn plain() {
+    let m3u = b"S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Z plyt/02 Animal Kingdom.mp3";
+    let mut buf = Vec::new();
+    transcode_m3u_to_pls(&mut &m3u[..], &mut buf).unwrap();
+
+    let elems: Vec<_> = ["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", "Z plyt/02 Animal Kingdom.mp3"]
+        .iter()
+        .map(|p| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None })
+        .collect();
+    assert_eq!(buf, pls::to_bytes(&elems));
+}
+
+#[test]
+fn odd_extinf() {
+    let lengths: Vec<_> = transcode(b"#EXTINF:,No length\n1.mp3\n\
+                                      #EXTINF:abc,Bad length\n2.mp3\n\
+                                      #EXTINF:-100,\n3.mp3\n\
+                                      #EXTINF:124\n4.mp3\n\
+                                      #EXTINF:1,Overridden\n#EXTINF:2,Title, with comma\n5.mp3\n\
+                                      #EXTINF:3,Dangling\n")
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.path, e.title, e.len))
+        .collect();
+    assert_eq!(lengths,
+               [("1.mp3".to_string(), Some("No length".to_string()), ElementLength::Unknown),
+                ("2.mp3".to_string(), Some("Bad length".to_string()), ElementLength::Unknown),
+                ("3.mp3".to_string(), None, ElementLength::Unknown),
+                ("4.mp3".to_string(), None, ElementLength::Seconds(124)),
+                ("5.mp3".to_string(), Some("Title, with comma".to_string()), ElementLength::Seconds(2))]);
+}
+
+#[test]
+fn empty() {
+    assert_eq!(transcode(b"").unwrap(), []);
+    assert_eq!(transcode(b"#EXTM3U\n\n").unwrap(), []);
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut buf = Vec::new();
+    assert_eq!(transcode_m3u_to_pls(&mut &b"#EXTM3U\nCaf\xE9.mp3\n"[..], &mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn write_round_trip() {
+    let elems = vec![PlaylistElement {
+                         path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                         title: Some("A-F-R-O & NGHTMRE, Stronger".to_string()),
+                         len: ElementLength::Seconds(230),
+                         genre: Some("Trap".to_string()),
+                     },
+                     PlaylistElement {
+                         path: "Track 2.mp3".to_string(),
+                         title: None,
+                         len: ElementLength::Seconds(79),
+                         genre: None,
+                     },
+                     PlaylistElement {
+                         path: "http://127.0.0.1:8002/stream".to_string(),
+                         title: Some("Unknown Radio".to_string()),
+                         len: ElementLength::Unknown,
+                         genre: None,
+                     },
+                     PlaylistElement {
+                         path: "Track 4.mp3".to_string(),
+                         title: None,
+                         len: ElementLength::Unknown,
+                         genre: Some("Rock".to_string()),
+                     }];
+
+    let mut m3u = Vec::new();
+    write_m3u(&elems, &mut m3u).unwrap();
+    assert_eq!(String::from_utf8(m3u.clone()).unwrap(),
+               "#EXTM3U\n\
+                #EXTINF:230,A-F-R-O & NGHTMRE, Stronger\n\
+                #EXTGENRE:Trap\n\
+                S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                #EXTINF:79,\n\
+                Track 2.mp3\n\
+                #EXTINF:-1,Unknown Radio\n\
+                http://127.0.0.1:8002/stream\n\
+                #EXTGENRE:Rock\n\
+                Track 4.mp3\n");
+    assert_eq!(transcode(&m3u).unwrap(), elems);
+
+    let mut m3u = Vec::new();
+    write_m3u(&[] as &[PlaylistElement], &mut m3u).unwrap();
+    assert_eq!(m3u, b"#EXTM3U\n");
+}
+
+#[test]
+fn write_line_break() {
+    let elem = PlaylistElement {
+        path: "Track 1.mp3".to_string(),
+        title: None,
+        len: ElementLength::Unknown,
+        genre: None,
+    };
+    let broken = [PlaylistElement { path: "Track 1.mp3\n#EXTINF:1,Injected\nTrack 2.mp3".to_string(), ..elem.clone() },
+                  PlaylistElement { title: Some("Title\r\nInjected.mp3".to_string()), ..elem.clone() },
+                  PlaylistElement { genre: Some("Rock\rInjected.mp3".to_string()), ..elem.clone() }];
+    for broken in &broken {
+        let mut m3u = Vec::new();
+        assert_eq!(write_m3u(&[elem.clone(), broken.clone()], &mut m3u).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+        assert_eq!(m3u, b"#EXTM3U\nTrack 1.mp3\n");
+    }
+}
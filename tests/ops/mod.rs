@@ -0,0 +1,606 @@
+use pls::{Playlist, PlaylistElement, NormalizeOptions, PlaylistStats, ElementLength, enrich_from, eq_normalized, fill_lengths, filter_out, from_pairs,
+          from_tags, group_by_dir, local_paths, move_entry, normalize, paths, same_set, search, stats, swap_entries, to_bare_paths, to_file_urls};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "url")]
+use url::Url;
+
+
+#[test]
+fn fill_lengths_unknown_only() {
+    let mut elems = vec![PlaylistElement {
+                             path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                             title: None,
+                             len: ElementLength::Unknown,
+                             genre: None,
+                         },
+                         PlaylistElement {
+                             path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                             title: None,
+                             len: ElementLength::Seconds(79),
+                             genre: None,
+                         },
+                         PlaylistElement {
+                             path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                             title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                             len: ElementLength::Unknown,
+                             genre: None,
+                         }];
+
+    let mut probed = Vec::new();
+    fill_lengths(&mut elems, |e| {
+        probed.push(e.path.clone());
+        if e.title.is_some() {
+            Some(Duration::from_secs(124))
+        } else {
+            None
+        }
+    });
+
+    assert_eq!(probed,
+               vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                    "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string()]);
+    assert_eq!(elems.iter().map(|e| e.len).collect::<Vec<_>>(),
+               vec![ElementLength::Unknown, ElementLength::Seconds(79), ElementLength::Seconds(124)]);
+}
+
+#[test]
+fn stats_empty() {
+    assert_eq!(stats(&[]),
+               PlaylistStats {
+                   count: 0,
+                   known_lengths: 0,
+                   unknown_lengths: 0,
+                   total_known: Duration::from_secs(0),
+                   has_urls: false,
+                   has_local: false,
+               });
+}
+
+#[test]
+fn stats_saturating() {
+    let elem = |len| {
+        PlaylistElement {
+            path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+            title: None,
+            len,
+            genre: None,
+        }
+    };
+    let elems = [elem(ElementLength::Seconds(u64::MAX)), elem(ElementLength::Seconds(u64::MAX)), elem(ElementLength::Seconds(1))];
+    assert_eq!(stats(&elems).total_known, Duration::MAX);
+    assert_eq!(stats(&elems[..1]).total_known, Duration::from_secs(u64::MAX));
+}
+
+#[test]
+fn stats_local_only() {
+    assert_eq!(stats(&[PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(79),
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                           title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                           len: ElementLength::Seconds(124),
+                           genre: None,
+                       }]),
+               PlaylistStats {
+                   count: 3,
+                   known_lengths: 2,
+                   unknown_lengths: 1,
+                   total_known: Duration::from_secs(79 + 124),
+                   has_urls: false,
+                   has_local: true,
+               });
+}
+
+#[test]
+fn stats_urls_only() {
+    assert_eq!(stats(&[PlaylistElement {
+                           path: "http://127.0.0.1:8002/%D0%BC%D1%83%D0%B7%D1%8B%D0%BA%D0%B0/Z%20p%C5%82yt/\
+                                  A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3"
+                               .to_string(),
+                           title: Some("A-F-R-O - CODE 829".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]),
+               PlaylistStats {
+                   count: 1,
+                   known_lengths: 0,
+                   unknown_lengths: 1,
+                   total_known: Duration::from_secs(0),
+                   has_urls: true,
+                   has_local: false,
+               });
+}
+
+fn messy() -> Vec<PlaylistElement> {
+    vec![PlaylistElement {
+             path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+             title: Some(" A-F-R-O & NGHTMRE - Stronger\t".to_string()),
+             len: ElementLength::Unknown,
+             genre: None,
+         },
+         PlaylistElement {
+             path: "".to_string(),
+             title: Some("A-F-R-O - Now Playing".to_string()),
+             len: ElementLength::Unknown,
+             genre: None,
+         },
+         PlaylistElement {
+             path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+             title: None,
+             len: ElementLength::Seconds(79),
+             genre: None,
+         },
+         PlaylistElement {
+             path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+             title: Some("   ".to_string()),
+             len: ElementLength::Seconds(124),
+             genre: None,
+         }]
+}
+
+#[test]
+fn normalize_all() {
+    let mut elems = messy();
+    normalize(&mut elems, NormalizeOptions::default());
+    assert_eq!(elems,
+               vec![PlaylistElement {
+                        path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                        title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                        len: ElementLength::Unknown,
+                        genre: None,
+                    },
+                    PlaylistElement {
+                        path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                        title: None,
+                        len: ElementLength::Seconds(124),
+                        genre: None,
+                    }]);
+}
+
+#[test]
+fn normalize_none() {
+    let mut elems = messy();
+    normalize(&mut elems,
+              NormalizeOptions {
+                  trim_titles: false,
+                  drop_empty_paths: false,
+                  dedup_by_path: false,
+              });
+    assert_eq!(elems, messy());
+}
+
+#[test]
+fn normalize_each() {
+    let mut elems = messy();
+    normalize(&mut elems,
+              NormalizeOptions {
+                  trim_titles: true,
+                  drop_empty_paths: false,
+                  dedup_by_path: false,
+              });
+    assert_eq!(elems.iter().map(|e| e.title.clone()).collect::<Vec<_>>(),
+               vec![Some("A-F-R-O & NGHTMRE - Stronger".to_string()), Some("A-F-R-O - Now Playing".to_string()), None, None]);
+
+    let mut elems = messy();
+    normalize(&mut elems,
+              NormalizeOptions {
+                  trim_titles: false,
+                  drop_empty_paths: true,
+                  dedup_by_path: false,
+              });
+    assert_eq!(elems, vec![messy()[0].clone(), messy()[2].clone(), messy()[3].clone()]);
+
+    let mut elems = messy();
+    normalize(&mut elems,
+              NormalizeOptions {
+                  trim_titles: false,
+                  drop_empty_paths: false,
+                  dedup_by_path: true,
+              });
+    assert_eq!(elems, vec![messy()[0].clone(), messy()[1].clone(), messy()[3].clone()]);
+}
+
+fn mixed() -> Vec<PlaylistElement> {
+    ["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+     "http://127.0.0.1:8002/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3",
+     r"\\Server\Music\A-F-R-O - Animal Kingdom.mp3",
+     "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3",
+     "pobrany/Activated Trap Locks.mp3",
+     "http://[::1/broken"]
+        .iter()
+        .map(|p| {
+            PlaylistElement {
+                path: p.to_string(),
+                title: None,
+                len: ElementLength::Unknown,
+                genre: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn same_set_reordered() {
+    let mut reordered = mixed();
+    reordered.reverse();
+    reordered[0].title = Some("A-F-R-O - Broken".to_string());
+    reordered[1].len = ElementLength::Seconds(124);
+
+    assert!(same_set(&mixed(), &reordered));
+    assert!(same_set(&[], &[]));
+}
+
+#[test]
+fn same_set_different() {
+    let mut changed = mixed();
+    changed[0].path = "S:/M J U Z I K/pobrany/Stronger.mp3".to_string();
+    assert!(!same_set(&mixed(), &changed));
+
+    let mut duplicated = mixed();
+    duplicated[1] = duplicated[0].clone();
+    assert!(!same_set(&mixed(), &duplicated));
+
+    assert!(!same_set(&mixed(), &mixed()[1..]));
+}
+
+#[test]
+fn eq_normalized_separators() {
+    let mut flipped = mixed();
+    for elem in &mut flipped {
+        if !elem.is_url() {
+            elem.path = elem.path.chars().map(|c| if c == '/' { '\\' } else if c == '\\' { '/' } else { c }).collect();
+        }
+    }
+    flipped[0].path = format!(" {}\t", flipped[0].path);
+    assert!(eq_normalized(&mixed(), &flipped));
+    assert!(eq_normalized(&flipped, &mixed()));
+    assert!(!same_set(&mixed(), &flipped));
+    assert!(eq_normalized(&[], &[]));
+
+    let dirs = |ps: &[&str]| {
+        ps.iter().map(|p| PlaylistElement { path: p.to_string(), title: None, len: ElementLength::Unknown, genre: None }).collect::<Vec<_>>()
+    };
+    assert!(eq_normalized(&dirs(&["/srv/Music/", "C:\\", "\\", "S:pobrany\\"]), &dirs(&["/srv/Music", "C:/", "/", "S:pobrany"])));
+    assert!(!eq_normalized(&dirs(&["C:\\"]), &dirs(&["C:"])));
+    assert!(!eq_normalized(&dirs(&["/"]), &dirs(&[""])));
+    assert!(!eq_normalized(&dirs(&["http://127.0.0.1:8002/stream/"]), &dirs(&["http://127.0.0.1:8002/stream"])));
+    assert!(!eq_normalized(&dirs(&["S:/Music/Track 1.mp3"]), &dirs(&["S:/music/track 1.mp3"])));
+}
+
+#[test]
+fn eq_normalized_different() {
+    let mut changed = mixed();
+    changed[2].title = Some("A-F-R-O - Animal Kingdom".to_string());
+    assert!(!eq_normalized(&mixed(), &changed));
+
+    let mut reordered = mixed();
+    reordered.swap(0, 4);
+    assert!(!eq_normalized(&mixed(), &reordered));
+
+    assert!(!eq_normalized(&mixed(), &mixed()[1..]));
+}
+
+#[test]
+fn from_pairs_scanned() {
+    let scanned: Vec<_> = mixed().into_iter().enumerate().map(|(i, e)| (e.path, ElementLength::Seconds(i as u64 * 60))).collect();
+    let elems = from_pairs(scanned.clone());
+
+    assert_eq!(paths(&elems), paths(&mixed()));
+    assert_eq!(elems.iter().map(|e| e.len).collect::<Vec<_>>(), scanned.iter().map(|p| p.1).collect::<Vec<_>>());
+    assert!(elems.iter().all(|e| e.title.is_none() && e.genre.is_none()));
+    assert_eq!(elems, scanned.into_iter().map(PlaylistElement::from).collect::<Vec<_>>());
+}
+
+#[test]
+fn move_entry_bounds() {
+    let mut elems = mixed();
+    move_entry(&mut elems, 4, 1).unwrap();
+    let mut expected = mixed();
+    expected[1..5].rotate_right(1);
+    assert_eq!(elems, expected);
+
+    move_entry(&mut elems, 1, 4).unwrap();
+    assert_eq!(elems, mixed());
+
+    move_entry(&mut elems, 5, 5).unwrap();
+    assert_eq!(elems, mixed());
+
+    assert_eq!(move_entry(&mut elems, 6, 0), Err(6));
+    assert_eq!(move_entry(&mut elems, 0, 6), Err(6));
+    assert_eq!(move_entry(&mut vec![], 0, 0), Err(0));
+    assert_eq!(elems, mixed());
+}
+
+#[test]
+fn playlist_insert_remove_bounds() {
+    let mut playlist = Playlist {
+        name: Some("A-F-R-O".to_string()),
+        entries: mixed(),
+    };
+    let extra = PlaylistElement {
+        path: "S:/M J U Z I K/pobrany/Broken.mp3".to_string(),
+        title: None,
+        len: ElementLength::Seconds(124),
+        genre: None,
+    };
+
+    playlist.insert_at(0, extra.clone());
+    playlist.insert_at(3, extra.clone());
+    playlist.insert_at(8, extra.clone());
+    playlist.insert_at(usize::MAX, extra.clone());
+    assert_eq!(playlist.entries.len(), mixed().len() + 4);
+    assert_eq!(playlist.entries.iter().enumerate().filter(|&(_, e)| *e == extra).map(|(i, _)| i).collect::<Vec<_>>(), [0, 3, 8, 9]);
+
+    assert_eq!(playlist.remove_at(10), None);
+    assert_eq!(playlist.remove_at(usize::MAX), None);
+    for &i in &[9, 8, 3, 0] {
+        assert_eq!(playlist.remove_at(i), Some(extra.clone()));
+    }
+    assert_eq!(playlist.entries, mixed());
+
+    let mut empty = Playlist::default();
+    assert_eq!(empty.remove_at(0), None);
+    empty.insert_at(1, extra.clone());
+    assert_eq!(empty.entries, [extra]);
+}
+
+#[test]
+fn swap_entries_bounds() {
+    let mut elems = mixed();
+    swap_entries(&mut elems, 5, 0).unwrap();
+    assert_eq!(elems[0], mixed()[5]);
+    assert_eq!(elems[5], mixed()[0]);
+    assert_eq!(elems[1..5], mixed()[1..5]);
+
+    swap_entries(&mut elems, 0, 5).unwrap();
+    swap_entries(&mut elems, 2, 2).unwrap();
+    assert_eq!(elems, mixed());
+
+    assert_eq!(swap_entries(&mut elems, 6, 0), Err(6));
+    assert_eq!(swap_entries(&mut elems, 0, usize::MAX), Err(usize::MAX));
+    assert_eq!(elems, mixed());
+}
+
+#[test]
+fn filter_out_order() {
+    let mut elems = mixed();
+    let removed = filter_out(&mut elems, |e| e.is_url());
+    let all = mixed();
+    assert_eq!(elems, [all[0].clone(), all[2].clone(), all[4].clone()]);
+    assert_eq!(removed, [all[1].clone(), all[3].clone(), all[5].clone()]);
+
+    assert_eq!(filter_out(&mut elems, |_| false), vec![]);
+    let rest = elems.clone();
+    assert_eq!(filter_out(&mut elems, |_| true), rest);
+    assert_eq!(elems, vec![]);
+}
+
+#[test]
+fn paths_all() {
+    assert_eq!(paths(&mixed()),
+               vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                    "http://127.0.0.1:8002/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3",
+                    r"\\Server\Music\A-F-R-O - Animal Kingdom.mp3",
+                    "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3",
+                    "pobrany/Activated Trap Locks.mp3",
+                    "http://[::1/broken"]);
+}
+
+#[test]
+fn local_paths_only() {
+    assert_eq!(local_paths(&mixed()),
+               vec![PathBuf::from("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3"),
+                    PathBuf::from(r"\\Server\Music\A-F-R-O - Animal Kingdom.mp3"),
+                    PathBuf::from("pobrany/Activated Trap Locks.mp3")]);
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn urls_only() {
+    assert_eq!(pls::urls(&mixed()),
+               vec![Url::parse("http://127.0.0.1:8002/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3").unwrap(),
+                    Url::parse("file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3").unwrap()]);
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn repair_mojibake_conservative() {
+    let titled = |t: Option<&str>| {
+        PlaylistElement {
+            path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+            title: t.map(str::to_string),
+            len: ElementLength::Unknown,
+            genre: None,
+        }
+    };
+    let mut elems = vec![titled(Some("A-F-R-O â€” PobraÅ„ podkÅ‚ad")),
+                         titled(Some("A-F-R-O \u{2014} Pobra\u{0139}\u{0084} podk\u{0139}\u{0082}ad")),
+                         titled(Some("Ã©tÃ© ðŸŽµ")),
+                         titled(Some("A-F-R-O - Animal Kingdom")),
+                         titled(Some("A-F-R-O — Pobrań podkład")),
+                         titled(Some("Café")),
+                         titled(Some("Ã")),
+                         titled(None)];
+    pls::repair_mojibake(&mut elems);
+
+    assert_eq!(elems.iter().map(|e| e.title.as_ref().map(|t| &t[..])).collect::<Vec<_>>(),
+               vec![Some("A-F-R-O — Pobrań podkład"),
+                    Some("A-F-R-O \u{2014} Pobra\u{0139}\u{0084} podk\u{0139}\u{0082}ad"),
+                    Some("été 🎵"),
+                    Some("A-F-R-O - Animal Kingdom"),
+                    Some("A-F-R-O — Pobrań podkład"),
+                    Some("Café"),
+                    Some("Ã"),
+                    None]);
+}
+
+#[test]
+fn from_tags_scanned() {
+    let scanned: Vec<_> = mixed()
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| {
+            (PathBuf::from(e.path),
+             if i % 2 == 0 { Some(format!("A-F-R-O - {}", i)) } else { None },
+             if i % 3 == 0 { Some(Duration::from_millis(i as u64 * 60_999)) } else { None })
+        })
+        .collect();
+    let elems = from_tags(scanned.clone());
+
+    assert_eq!(paths(&elems), paths(&mixed()));
+    assert_eq!(elems.iter().map(|e| e.title.clone()).collect::<Vec<_>>(), scanned.iter().map(|t| t.1.clone()).collect::<Vec<_>>());
+    assert_eq!(elems.iter().map(|e| e.len).collect::<Vec<_>>(),
+               vec![ElementLength::Seconds(0), ElementLength::Unknown, ElementLength::Unknown, ElementLength::Seconds(182), ElementLength::Unknown,
+                    ElementLength::Unknown]);
+    assert!(elems.iter().all(|e| e.genre.is_none()));
+}
+
+#[test]
+fn file_urls_round_trip() {
+    let mut elems = mixed();
+    elems.push(PlaylistElement {
+        path: "Z płyt/A-F-R-O - Tales From The Basement/03 #CODE 829; 100%?.mp3".to_string(),
+        title: None,
+        len: ElementLength::Unknown,
+        genre: None,
+    });
+    to_file_urls(&mut elems, Path::new("/srv/M J U Z I K"));
+    assert_eq!(paths(&elems),
+               vec!["file:///S:/M%20J%20U%20Z%20I%20K/pobrany/A-F-R-O%20&%20NGHTMRE%20-%20Stronger.mp3",
+                    "http://127.0.0.1:8002/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3",
+                    "file://Server/Music/A-F-R-O%20-%20Animal%20Kingdom.mp3",
+                    "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3",
+                    "file:///srv/M%20J%20U%20Z%20I%20K/pobrany/Activated%20Trap%20Locks.mp3",
+                    "http://[::1/broken",
+                    "file:///srv/M%20J%20U%20Z%20I%20K/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829%3B%20100%25%3F.mp3"]);
+
+    to_bare_paths(&mut elems);
+    assert_eq!(paths(&elems),
+               vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                    "http://127.0.0.1:8002/Z%20p%C5%82yt/A-F-R-O%20-%20Tales%20From%20The%20Basement/03%20%23CODE%20829.mp3",
+                    r"\\Server\Music\A-F-R-O - Animal Kingdom.mp3",
+                    "S:/M J U Z I K/pobrany/Stronger.mp3",
+                    "/srv/M J U Z I K/pobrany/Activated Trap Locks.mp3",
+                    "http://[::1/broken",
+                    "/srv/M J U Z I K/Z płyt/A-F-R-O - Tales From The Basement/03 #CODE 829; 100%?.mp3"]);
+
+    let mut elems = vec![PlaylistElement {
+                             path: "file://localhost/srv/Music/%FF.mp3".to_string(),
+                             title: None,
+                             len: ElementLength::Unknown,
+                             genre: None,
+                         },
+                         PlaylistElement {
+                             path: "FILE://localhost/srv/Music/Track%201.mp3".to_string(),
+                             title: None,
+                             len: ElementLength::Unknown,
+                             genre: None,
+                         }];
+    to_bare_paths(&mut elems);
+    assert_eq!(paths(&elems), vec!["file://localhost/srv/Music/%FF.mp3", "/srv/Music/Track 1.mp3"]);
+}
+
+#[test]
+fn enrich_from_partial_overlap() {
+    let elem = |path: &str, title: Option<&str>, len| {
+        PlaylistElement {
+            path: path.to_string(),
+            title: title.map(str::to_string),
+            len,
+            genre: None,
+        }
+    };
+    let master = [elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("A-F-R-O & NGHTMRE - Stronger"), ElementLength::Seconds(218)),
+                  elem("S:/M J U Z I K/pobrany/Animal Kingdom.mp3", None, ElementLength::Seconds(124)),
+                  elem("S:/M J U Z I K/pobrany/Animal Kingdom.mp3", Some("A-F-R-O - Animal Kingdom"), ElementLength::Seconds(125)),
+                  elem("S:/M J U Z I K/pobrany/Prologue.mp3", Some("A-F-R-O - Prologue"), ElementLength::Seconds(60))];
+    let mut device = vec![elem("S:/M J U Z I K/pobrany/Animal Kingdom.mp3", None, ElementLength::Unknown),
+                          elem("s:/m j u z i k/pobrany/prologue.mp3", None, ElementLength::Unknown),
+                          elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("Stronger"), ElementLength::Unknown),
+                          elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", None, ElementLength::Seconds(1))];
+
+    enrich_from(&mut device, &master);
+    assert_eq!(device,
+               vec![elem("S:/M J U Z I K/pobrany/Animal Kingdom.mp3", None, ElementLength::Seconds(124)),
+                    elem("s:/m j u z i k/pobrany/prologue.mp3", None, ElementLength::Unknown),
+                    elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("Stronger"), ElementLength::Seconds(218)),
+                    elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("A-F-R-O & NGHTMRE - Stronger"), ElementLength::Seconds(1))]);
+
+    let before = device.clone();
+    enrich_from(&mut device, &[]);
+    assert_eq!(device, before);
+}
+
+#[test]
+fn search_titles_and_file_names() {
+    let elems: Vec<_> = [("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", None),
+                         ("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3", Some("A-F-R-O - Animal Kingdom")),
+                         ("http://127.0.0.1:8002/stream", Some("Unknown Radio")),
+                         ("S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3", None)]
+        .iter()
+        .map(|&(path, title)| {
+            PlaylistElement {
+                path: path.to_string(),
+                title: title.map(str::to_string),
+                len: ElementLength::Unknown,
+                genre: None,
+            }
+        })
+        .collect();
+
+    assert_eq!(search(&elems, "a-f-r-o"), [&elems[0], &elems[1]]);
+    assert_eq!(search(&elems, "basement"), Vec::<&PlaylistElement>::new());
+    assert_eq!(search(&elems, "STREAM"), [&elems[2]]);
+    assert_eq!(search(&elems, ""), elems.iter().collect::<Vec<_>>());
+    assert_eq!(search(&[], "A-F-R-O"), Vec::<&PlaylistElement>::new());
+}
+
+#[test]
+fn group_by_dir_mixed() {
+    let elems: Vec<_> = ["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                         "http://127.0.0.1:8002/stream",
+                         r"S:\M J U Z I K\pobrany\Animal Kingdom.mp3",
+                         "Stronger.mp3",
+                         r"\\Server\Music\Stronger.mp3",
+                         "/Stronger.mp3",
+                         r"S:\Stronger.mp3",
+                         "S:Stronger.mp3",
+                         "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3",
+                         "S:/M J U Z I K/pobrany/Animal Kingdom.mp3",
+                         "pobrany/Stronger.mp3"]
+        .iter()
+        .map(|path| {
+            PlaylistElement {
+                path: path.to_string(),
+                title: None,
+                len: ElementLength::Unknown,
+                genre: None,
+            }
+        })
+        .collect();
+
+    let groups = group_by_dir(&elems);
+    let groups: Vec<_> = groups.iter().map(|(dir, elems)| (&dir[..], elems.iter().map(|e| &e.path[..]).collect::<Vec<_>>())).collect();
+    assert_eq!(groups,
+               [("", vec!["Stronger.mp3"]),
+                ("/", vec!["/Stronger.mp3"]),
+                ("<url>", vec!["http://127.0.0.1:8002/stream", "file:///S:/M%20J%20U%20Z%20I%20K/pobrany/Stronger.mp3"]),
+                ("S:", vec!["S:Stronger.mp3"]),
+                ("S:/M J U Z I K/pobrany", vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", "S:/M J U Z I K/pobrany/Animal Kingdom.mp3"]),
+                (r"S:\", vec![r"S:\Stronger.mp3"]),
+                (r"S:\M J U Z I K\pobrany", vec![r"S:\M J U Z I K\pobrany\Animal Kingdom.mp3"]),
+                (r"\\Server\Music", vec![r"\\Server\Music\Stronger.mp3"]),
+                ("pobrany", vec!["pobrany/Stronger.mp3"])]);
+    assert!(group_by_dir(&[]).is_empty());
+}
@@ -1,12 +1,23 @@
-use pls::{ParseError, parse};
+use pls::{ParseOptions, ParseError, is_valid_pls, parse, parse_with, parse_concat};
 use ini::ini::Error as IniError;
+use std::error::Error;
+use std::io::{self, Read};
+
+
+fn invalid_integer(key: &str, value: &str) -> ParseError {
+    ParseError::InvalidInteger {
+        key: key.to_string(),
+        value: value.to_string(),
+        source: value.parse::<u64>().unwrap_err(),
+    }
+}
 
 
 #[test]
 fn invalid_version() {
     assert_eq!(parse(&mut &b"[playlist]\n\
                              Version=-1\n"[..]),
-               Err(ParseError::InvalidInteger(u64::from_str_radix("-1", 10).unwrap_err())));
+               Err(invalid_integer("Version", "-1")));
     assert_eq!(parse(&mut &b"[playlist]\n\
                              Version=0\n"[..]),
                Err(ParseError::InvalidVersion(0)));
@@ -32,7 +43,36 @@ fn missing_playlist_section() {
 fn missing_number_of_entries() {
     assert_eq!(parse(&mut &b"[playlist]\n\
                              File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n"[..]),
-               Err(ParseError::MissingKey("NumberOfEntries|numberofentries|NumberOfEvents".to_string())));
+               Err(ParseError::MissingKey {
+                   key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+                   entry: None,
+               }));
+}
+
+#[test]
+fn missing_version() {
+    let opts = ParseOptions { require_version: true, ..ParseOptions::default() };
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  NumberOfEntries=1\n"[..],
+                          &opts),
+               Err(ParseError::MissingKey {
+                   key: "Version".to_string(),
+                   entry: None,
+               }));
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  version=2\n\
+                                  NumberOfEntries=0\n"[..],
+                          &opts),
+               Err(ParseError::MissingKey {
+                   key: "Version".to_string(),
+                   entry: None,
+               }));
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  NumberOfEntries=0\n\
+                                  Version=3\n"[..],
+                          &opts),
+               Err(ParseError::InvalidVersion(3)));
 }
 
 #[test]
@@ -42,14 +82,46 @@ fn missing_file_entry() {
                              File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
                              NumberOfEntries=3"
                                [..]),
-               Err(ParseError::MissingKey("File3".to_string())));
+               Err(ParseError::MissingKey {
+                   key: "File3".to_string(),
+                   entry: Some(3),
+               }));
+}
+
+#[test]
+fn noncontiguous_indices() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             File10=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                             File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                             NumberOfEntries=3"
+                               [..]),
+               Err(ParseError::MissingKey {
+                   key: "File3".to_string(),
+                   entry: Some(3),
+               }));
+}
+
+#[test]
+fn not_valid_pls() {
+    for data in &[&b""[..],
+                  &b"[playlist]\n"[..],
+                  &b"[playlist]\nVersion=3\nNumberOfEntries=0\n"[..],
+                  &b"[playlist]\nLength1=420\nNumberOfEntries=1\n"[..],
+                  &b"[playlist]\nFile1=Track 1.mp3\nLength1=Track 1\nNumberOfEntries=1\n"[..],
+                  &b"[playlist]\nFile1=Track 1.mp3\nNumberOfEntries=2\n"[..],
+                  &b"[playlist]\nFile1=Track 1.mp3\nFile2=Track 2.mp3\nNumberOfEntries=1\n"[..],
+                  &b"[playlist]\nFile1=Track 1.mp3\nFile3=Track 3.mp3\nNumberOfEntries=1\n"[..],
+                  &b"[playlist]\nFile1=Track 1.mp3\nFile3=Track 3.mp3\nNumberOfEntries=2\n"[..]] {
+        assert!(!is_valid_pls(data), "{}", String::from_utf8_lossy(data));
+    }
 }
 
 #[test]
 fn invalid_number_of_entries() {
     assert_eq!(parse(&mut &b"[playlist]\n\
                              NumberOfEntries=-1"[..]),
-               Err(ParseError::InvalidInteger(u64::from_str_radix("-1", 10).unwrap_err())));
+               Err(invalid_integer("NumberOfEntries", "-1")));
 }
 
 #[test]
@@ -59,7 +131,126 @@ fn invalid_length() {
                              Length1=Abolish the Burgeoisie!\n\
                              NumberOfEntries=1"
                                [..]),
-               Err(ParseError::InvalidInteger(u64::from_str_radix("Abolish the Burgeoisie!", 10).unwrap_err())));
+               Err(invalid_integer("Length1", "Abolish the Burgeoisie!")));
+}
+
+#[test]
+fn fractional_length() {
+    let opts = ParseOptions { round_fractional_lengths: true, ..ParseOptions::default() };
+    for &(value, opts) in &[("180.5", &ParseOptions::default()), ("180.", &opts), (".5", &opts), ("-1.0", &opts), ("1.5e2", &opts), ("1.-5", &opts)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), opts), Err(invalid_integer("Length1", value)));
+    }
+
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  Length1=99999999999999999999999.5\n\
+                                  NumberOfEntries=1"
+                                    [..],
+                          &opts),
+               Err(ParseError::LengthOverflow {
+                   key: "Length1".to_string(),
+                   value: "99999999999999999999999.5".to_string(),
+               }));
+}
+
+#[test]
+fn clock_length() {
+    let opts = ParseOptions { allow_clock_lengths: true, ..ParseOptions::default() };
+    for &(value, opts) in &[("3:40", &ParseOptions::default()),
+                            ("3:60", &opts),
+                            ("3:4", &opts),
+                            (":40", &opts),
+                            ("1:60:00", &opts),
+                            ("1:2:03", &opts),
+                            ("1:02:03:04", &opts),
+                            ("-3:40", &opts),
+                            ("3:40.5", &opts)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), opts), Err(invalid_integer("Length1", value)), "{}", value);
+    }
+
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  Length1=99999999999999999999999:00:00\n\
+                                  NumberOfEntries=1"
+                                    [..],
+                          &opts),
+               Err(ParseError::LengthOverflow {
+                   key: "Length1".to_string(),
+                   value: "99999999999999999999999:00:00".to_string(),
+               }));
+}
+
+#[test]
+fn negative_length() {
+    let opts = ParseOptions { negative_length_is_unknown: true, ..ParseOptions::default() };
+    for &(value, opts) in &[("-2", &ParseOptions::default()),
+                            ("-100", &ParseOptions::default()),
+                            ("-", &opts),
+                            ("--2", &opts),
+                            ("-2.5", &opts),
+                            ("-2.5", &ParseOptions { round_fractional_lengths: true, ..opts.clone() }),
+                            ("- 2", &opts)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        match parse_with(&mut data.as_bytes(), opts) {
+            Err(ParseError::InvalidInteger { ref key, value: ref v, .. }) => assert_eq!((&key[..], &v[..]), ("Length1", value)),
+            r => panic!("{}: {:?}", value, r),
+        }
+    }
+}
+
+#[test]
+fn length_unit() {
+    let opts = ParseOptions { allow_length_units: true, ..ParseOptions::default() };
+    for &(value, opts) in &[("420s", &ParseOptions::default()),
+                            ("420 sec", &ParseOptions::default()),
+                            ("s", &opts),
+                            ("420ss", &opts),
+                            ("420 seconds", &opts),
+                            ("420ms", &opts)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        match parse_with(&mut data.as_bytes(), opts) {
+            Err(ParseError::InvalidInteger { ref key, value: ref v, .. }) => assert_eq!((&key[..], &v[..]), ("Length1", value)),
+            r => panic!("{}: {:?}", value, r),
+        }
+    }
+}
+
+#[test]
+fn length_overflow() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             Length1=99999999999999999999999\n\
+                             NumberOfEntries=1"
+                               [..]),
+               Err(ParseError::LengthOverflow {
+                   key: "Length1".to_string(),
+                   value: "99999999999999999999999".to_string(),
+               }));
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  Length1=-99999999999999999999999\n\
+                                  NumberOfEntries=1"
+                                    [..],
+                          &ParseOptions { clamp_overflowing_lengths: true, ..ParseOptions::default() }),
+               Err(invalid_integer("Length1", "-99999999999999999999999")));
 }
 
 #[test]
@@ -71,3 +262,221 @@ fn ini() {
                    msg: r#"Expecting "[Some(']')]" but found EOF."#.to_string(),
                })));
 }
+
+#[test]
+fn missing_file_entry_allowed() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  Title3=A-F-R-O - Animal Kingdom\n\
+                                  NumberOfEntries=3"
+                                    [..],
+                          &ParseOptions { allow_missing_file: true, ..ParseOptions::default() }),
+               Err(ParseError::MissingKey {
+                   key: "File2".to_string(),
+                   entry: Some(2),
+               }));
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             Title1=A-F-R-O - Animal Kingdom\n\
+                             NumberOfEntries=1"
+                               [..]),
+               Err(ParseError::MissingKey {
+                   key: "File1".to_string(),
+                   entry: Some(1),
+               }));
+}
+
+#[test]
+fn missing_named_section() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  NumberOfEntries=1\n"[..],
+                          &ParseOptions { section_name: Some("Wiedergabeliste".to_string()), ..ParseOptions::default() }),
+               Err(ParseError::MissingPlaylistSection));
+}
+
+#[test]
+fn concat_reports_reader() {
+    assert_eq!(parse_concat(vec![&b"[playlist]\n\
+                                    NumberOfEntries=0\n"[..],
+                                 &b"[playlist]\n\
+                                    NumberOfEntries=0\n"[..],
+                                 &b"File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                    NumberOfEntries=1\n"[..],
+                                 &b"[playlist\n"[..]]),
+               Err((2, ParseError::MissingPlaylistSection)));
+}
+
+#[test]
+fn conflicting_counts() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  NumberOfEntries=3\n\
+                                  NumberOfEvents=5\n"[..],
+                          &ParseOptions { reject_conflicting_counts: true, ..ParseOptions::default() }),
+               Err(ParseError::ConflictingCountKeys));
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  NumberOfEntries=0\n\
+                                  numberofentries=-1\n"[..],
+                          &ParseOptions { reject_conflicting_counts: true, ..ParseOptions::default() }),
+               Err(invalid_integer("numberofentries", "-1")));
+}
+
+#[test]
+fn keys_before_section() {
+    assert_eq!(parse(&mut &b"Version=2\n\
+                             NumberOfEntries=1\n\
+                             [playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n"[..]),
+               Err(ParseError::MissingKey {
+                   key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+                   entry: None,
+               }));
+    assert_eq!(parse_with(&mut &b"Version=3\n\
+                                  [playlist]\n\
+                                  NumberOfEntries=0\n"[..],
+                          &ParseOptions { allow_keys_before_section: true, ..ParseOptions::default() }),
+               Err(ParseError::InvalidVersion(3)));
+}
+
+#[test]
+fn location() {
+    let err = parse(&mut &b"[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            [playlist"[..])
+        .unwrap_err();
+    assert_eq!((err.line(), err.column()), (Some(2), Some(9)));
+
+    let err = parse(&mut &b"[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1=Abolish the Burgeoisie!\n\
+                            NumberOfEntries=1"[..])
+        .unwrap_err();
+    assert_eq!((err.line(), err.column()), (None, None));
+}
+
+#[test]
+fn invalid_utf8() {
+    let err = parse(&mut &b"[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger\xFF.mp3\n\
+                            NumberOfEntries=1"[..])
+        .unwrap_err();
+    assert_eq!(err, ParseError::Utf8(68));
+    assert_eq!((err.line(), err.column()), (None, None));
+    assert_eq!(err.to_string(), "Input not valid UTF-8 at byte 68");
+}
+
+#[test]
+fn latin1() {
+    let data = b"[playlist]\n\
+                 File1=S:/Music/Caf\xE9.mp3\n\
+                 Title1=Caf\xE9 del Mar\n\
+                 NumberOfEntries=1\n";
+    let at = data.iter().position(|&b| b == 0xE9).unwrap();
+    assert_eq!(parse(&mut &data[..]), Err(ParseError::Utf8(at)));
+    assert_eq!(pls::parse_bytes(data), Err(ParseError::Utf8(at)));
+    assert_eq!(pls::parse_str(&String::from_utf8_lossy(data)).map(|e| e.len()), Ok(1));
+    assert_eq!(pls::Document::parse(&mut &data[..]).map(|_| ()), Err(ParseError::Utf8(at)));
+
+    for prefix in &[&b"\xEF\xBB\xBF"[..], b"[playlist]\nNumberOfEntries=0\n"] {
+        assert_eq!(pls::parse_multi(&mut &[prefix, &data[..]].concat()[..]), Err(ParseError::Utf8(prefix.len() + at)));
+    }
+    let prefixed = [&b"HTTP/1.1 200 OK\r\n\r\n"[..], data].concat();
+    assert_eq!(parse_with(&mut &prefixed[..], &ParseOptions { skip_to_section: true, ..ParseOptions::default() }),
+               Err(ParseError::Utf8(prefixed.len() - data.len() + at)));
+}
+
+#[test]
+fn reader_error() {
+    struct Reset;
+    impl Read for Reset {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer"))
+        }
+    }
+    let data = b"[playlist]\n\
+                 File1=http://127.0.0.1:8002/stream\n\
+                 NumberOfEn";
+    let expected = Err(ParseError::Io(io::ErrorKind::ConnectionReset));
+
+    assert_eq!(parse(&mut data.chain(Reset)), expected);
+    assert_eq!(parse_with(&mut data.chain(Reset), &ParseOptions { max_bytes: Some(1024), ..ParseOptions::default() }), expected);
+    assert_eq!(pls::parse_iter(&mut data.chain(Reset)).map(|_| ()), expected.clone().map(|_| ()));
+    assert_eq!(pls::parse_multi(&mut data.chain(Reset)).map(|_| ()), expected.clone().map(|_| ()));
+    assert_eq!(pls::Document::parse(&mut data.chain(Reset)).map(|_| ()), expected.clone().map(|_| ()));
+
+    let err = parse(&mut Reset).unwrap_err();
+    assert_eq!((err.line(), err.column()), (None, None));
+    assert_eq!(err.to_string(), "connection reset");
+    assert!(err.source().is_none());
+    assert_eq!(err.clone(), err);
+}
+
+#[test]
+fn empty() {
+    for data in &[&b""[..], b" ", b"\r\n\r\n", b"\t \n"] {
+        assert_eq!(parse(&mut &data[..]), Err(ParseError::Empty), "{:?}", data);
+        assert_eq!(pls::parse_prefix(data), Err(ParseError::Empty), "{:?}", data);
+        assert!(!is_valid_pls(data));
+    }
+    assert_eq!(parse_with(&mut &b"\n\n"[..], &ParseOptions { skip_to_section: true, ..ParseOptions::default() }),
+               Err(ParseError::Empty));
+
+    assert_eq!(parse(&mut &b"\n; Nothing\n"[..]), Err(ParseError::MissingPlaylistSection));
+    assert_eq!(pls::parse_prefix(b"\n; Nothing\n"), Err(ParseError::MissingPlaylistSection));
+    assert_eq!(ParseError::Empty.to_string(), "Empty input");
+}
+
+#[test]
+fn junk_before_section() {
+    assert_eq!(parse(&mut &b"HTTP/1.1 200 OK\r\n\
+                             \r\n\
+                             [playlist]\n\
+                             NumberOfEntries=0\n"[..]),
+               Err(ParseError::MissingPlaylistSection));
+    assert_eq!(parse_with(&mut &b"HTTP/1.1 200 OK\r\n\
+                                  \r\n\
+                                  [playlist\n\
+                                  NumberOfEntries=0\n"[..],
+                          &ParseOptions { skip_to_section: true, ..ParseOptions::default() }),
+               Err(ParseError::MissingPlaylistSection));
+}
+
+#[test]
+fn too_large() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 NumberOfEntries=1\n";
+    let opts = |max| ParseOptions { max_bytes: Some(max), ..ParseOptions::default() };
+
+    assert_eq!(parse_with(&mut &data[..], &opts(data.len())).map(|e| e.len()), Ok(1));
+    assert_eq!(parse_with(&mut &data[..], &opts(data.len() - 1)), Err(ParseError::TooLarge(data.len() - 1)));
+    assert_eq!(parse_with(&mut data.chain(io::repeat(b'\n')), &opts(1024 * 1024)), Err(ParseError::TooLarge(1024 * 1024)));
+    assert_eq!(ParseError::TooLarge(1024).to_string(), "Input longer than 1024 bytes");
+}
+
+#[test]
+fn display() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             Length1=Abolish the Burgeoisie!\n\
+                             NumberOfEntries=1"
+                               [..])
+                   .unwrap_err()
+                   .to_string(),
+               r#"Key "Length1" has invalid integer value "Abolish the Burgeoisie!": invalid digit found in string"#);
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             NumberOfEntries=2"
+                               [..])
+                   .unwrap_err()
+                   .to_string(),
+               r#"Entry 2 missing key "File2""#);
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             Length1=99999999999999999999999\n\
+                             NumberOfEntries=1"
+                               [..])
+                   .unwrap_err()
+                   .to_string(),
+               r#"Key "Length1" has length "99999999999999999999999" too big to represent"#);
+}
@@ -1,11 +1,14 @@
-use pls::{PlaylistElement, ElementLength, parse};
+use pls::{self, Playlist, PlaylistElement, ElementLength, ParseOptions, parse, parse_with, parse_indexed, parse_full, parse_iter, parse_iter_with, parse_visit,
+          parse_str, parse_bytes, parse_concat, parse_multi, parse_prefix};
+use std::ops::ControlFlow;
+use std::str;
 
 mod incorrect;
 
 
 #[test]
 fn correct() {
-    for number_of_entries in &["NumberOfEntries", "numberofentries", "NumberOfEvents"] {
+    for number_of_entries in &["NumberOfEntries", "numberofentries", "NumberOfEvents", "NUMBEROFENTRIES", "Numberofentries", "numberofevents"] {
         assert_eq!(parse(&mut format!("[playlist]\n\
                                        File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
                                        \n\
@@ -29,16 +32,19 @@ fn correct() {
                                path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
                                title: None,
                                len: ElementLength::Unknown,
+                               genre: None,
                            },
                            PlaylistElement {
                                path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
                                title: None,
                                len: ElementLength::Seconds(79),
+                               genre: None,
                            },
                            PlaylistElement {
                                path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
                                title: Some("A-F-R-O - Animal Kingdom".to_string()),
                                len: ElementLength::Seconds(124),
+                               genre: None,
                            },
                            PlaylistElement {
                                path: "http://127.0.0.1:8002/%D0%BC%D1%83%D0%B7%D1%8B%D0%BA%D0%B0/Z%20p%C5%82yt/\
@@ -46,6 +52,1187 @@ fn correct() {
                                    .to_string(),
                                title: Some("A-F-R-O - CODE 829".to_string()),
                                len: ElementLength::Unknown,
+                               genre: None,
                            }]));
     }
 }
+
+#[test]
+fn correct_genre() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=http://127.0.0.1:8002/stream\n\
+                             Title1=Unknown Radio\n\
+                             Length1=-1\n\
+                             Genre1=Electronic Dance\n\
+                             \n\
+                             File2=http://127.0.0.1:8003/stream\n\
+                             \n\
+                             NumberOfEntries=2\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "http://127.0.0.1:8002/stream".to_string(),
+                           title: Some("Unknown Radio".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: Some("Electronic Dance".to_string()),
+                       },
+                       PlaylistElement {
+                           path: "http://127.0.0.1:8003/stream".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_multidigit_indices() {
+    let elems: Vec<_> = (0..105)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:03}.mp3", i),
+                title: Some(format!("Track {}", i)),
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write(&elems, &mut buf).unwrap();
+    assert_eq!(parse(&mut &buf[..]), Ok(elems));
+}
+
+#[test]
+fn correct_indexed() {
+    let elems: Vec<_> = (0..105)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:03}.mp3", i),
+                title: None,
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write(&elems, &mut buf).unwrap();
+    assert_eq!(parse_indexed(&mut &buf[..], &ParseOptions::default()),
+               Ok(elems.into_iter().enumerate().map(|(i, e)| (i + 1, e)).collect()));
+}
+
+#[test]
+fn correct_missing_file_allowed() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  \n\
+                                  Title2=A-F-R-O - Animal Kingdom\n\
+                                  \n\
+                                  Length3=124\n\
+                                  \n\
+                                  NumberOfEntries=3\n"[..],
+                          &ParseOptions { allow_missing_file: true, ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "".to_string(),
+                           title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(124),
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_section_case() {
+    for section in &["Playlist", "PLAYLIST", "pLaYlIsT"] {
+        assert_eq!(parse(&mut format!("[{}]\n\
+                                       File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                       NumberOfEntries=1\n",
+                                      section)
+                       .as_bytes()),
+                   Ok(vec![PlaylistElement {
+                               path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                               title: None,
+                               len: ElementLength::Unknown,
+                               genre: None,
+                           }]));
+    }
+}
+
+#[test]
+fn correct_section_exact_preferred() {
+    assert_eq!(parse(&mut &b"[Playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             NumberOfEntries=1\n\
+                             [playlist]\n\
+                             File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                             NumberOfEntries=1\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_section_name() {
+    assert_eq!(parse_with(&mut &b"[Wiedergabeliste]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  NumberOfEntries=1\n"[..],
+                          &ParseOptions { section_name: Some("wiedergabeliste".to_string()), ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_concat() {
+    assert_eq!(parse_concat(vec![&b"[playlist]\n\
+                                    File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                    \n\
+                                    File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                                    Length2=79\n\
+                                    \n\
+                                    NumberOfEntries=2\n"[..],
+                                 &b"[playlist]\n\
+                                    NumberOfEntries=0\n"[..],
+                                 &b"[playlist]\n\
+                                    File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                                    Title1=A-F-R-O - Animal Kingdom\n\
+                                    Length1=124\n\
+                                    \n\
+                                    NumberOfEntries=1\n"[..]]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(79),
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                           title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                           len: ElementLength::Seconds(124),
+                           genre: None,
+                       }]));
+    assert_eq!(parse_concat(Vec::<&[u8]>::new()), Ok(vec![]));
+}
+
+#[test]
+fn correct_embedded_equals() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=http://127.0.0.1:8002/stream?mount=live&format=mp3\n\
+                             Title1=A-F-R-O = NGHTMRE - Stronger\n\
+                             \n\
+                             File2=S:/M J U Z I K/pobrany/a=b.mp3\n\
+                             Title2==\n\
+                             \n\
+                             NumberOfEntries=2\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "http://127.0.0.1:8002/stream?mount=live&format=mp3".to_string(),
+                           title: Some("A-F-R-O = NGHTMRE - Stronger".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/a=b.mp3".to_string(),
+                           title: Some("=".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_iter() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 \n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 Length2=79\n\
+                 \n\
+                 File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 Title3=A-F-R-O - Animal Kingdom\n\
+                 Length3=124\n\
+                 \n\
+                 NumberOfEntries=3\n";
+
+    let iter = parse_iter(&mut &data[..]).unwrap();
+    assert_eq!(iter.size_hint(), (3, Some(3)));
+    assert_eq!(iter.collect::<Result<Vec<_>, _>>(), parse(&mut &data[..]));
+}
+
+#[test]
+fn correct_iter_stops_early() {
+    assert_eq!(parse_iter(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  \n\
+                                  File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                                  Length2=79\n\
+                                  \n\
+                                  Length3=Abolish the Burgeoisie!\n\
+                                  \n\
+                                  NumberOfEntries=1000000000000\n"[..])
+                   .unwrap()
+                   .take(2)
+                   .collect::<Result<Vec<_>, _>>(),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(79),
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_conflicting_counts() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 NumberOfEvents=5\n\
+                 NumberOfEntries=1\n";
+    assert_eq!(parse(&mut &data[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_count_capitalisations() {
+    let entries = |counts: &str| {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                            {}",
+                           counts);
+        (parse(&mut data.as_bytes()).map(|e| e.len()),
+         parse_with(&mut data.as_bytes(), &ParseOptions { reject_conflicting_counts: true, ..ParseOptions::default() }).map(|e| e.len()))
+    };
+
+    assert_eq!(entries("NUMBEROFENTRIES=1\nNumberOfEntries=2\n"), (Ok(2), Err(pls::ParseError::ConflictingCountKeys)));
+    assert_eq!(entries("NumberOfEvents=2\nnumberOfEntries=1\n"), (Ok(1), Err(pls::ParseError::ConflictingCountKeys)));
+    assert_eq!(entries("NUMBEROFEVENTS=2\nnumberofevents=2\nNumberofentries=2\n"), (Ok(2), Ok(2)));
+    let missing = pls::ParseError::MissingKey {
+        key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+        entry: None,
+    };
+    assert_eq!(entries("NumberOfEntriesX=2\nNumberOfEvent=2\n"), (Err(missing.clone()), Err(missing)));
+}
+
+#[test]
+fn correct_agreeing_counts() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  numberofentries=1\n\
+                                  NumberOfEntries=1\n"[..],
+                          &ParseOptions { reject_conflicting_counts: true, ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+fn utf16(s: &str, bom: [u8; 2], unit: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut ret = bom.to_vec();
+    for c in s.encode_utf16() {
+        ret.extend_from_slice(&unit(c));
+    }
+    ret
+}
+
+#[test]
+fn correct_bytes() {
+    let data = "[playlist]\n\
+                File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                Title1=A-F-R-O — Animal Kingdom\n\
+                Length1=124\n\
+                \n\
+                NumberOfEntries=1\n";
+    let expected = Ok(vec![PlaylistElement {
+                               path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                               title: Some("A-F-R-O — Animal Kingdom".to_string()),
+                               len: ElementLength::Seconds(124),
+                               genre: None,
+                           }]);
+
+    assert_eq!(parse_bytes(data.as_bytes()), expected);
+    assert_eq!(parse_bytes(&[&b"\xEF\xBB\xBF"[..], data.as_bytes()].concat()), expected);
+    assert_eq!(parse_bytes(&utf16(data, [0xFF, 0xFE], u16::to_le_bytes)), expected);
+    assert_eq!(parse_bytes(&utf16(data, [0xFE, 0xFF], u16::to_be_bytes)), expected);
+    assert_eq!(parse_str(data), expected);
+    assert_eq!(parse_str(&format!("\u{FEFF}{}", data)), expected);
+}
+
+#[test]
+fn correct_fractional_lengths() {
+    for &(value, secs) in &[("180.5", 181),
+                            ("180.4999", 180),
+                            ("180.0", 180),
+                            ("0.5", 1),
+                            ("0.05", 0),
+                            ("180", 180),
+                            ("18446744073709551615.5", u64::MAX)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), &ParseOptions { round_fractional_lengths: true, ..ParseOptions::default() }),
+                   Ok(vec![PlaylistElement {
+                               path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                               title: None,
+                               len: ElementLength::Seconds(secs),
+                               genre: None,
+                           }]),
+                   "{}",
+                   value);
+    }
+}
+
+#[test]
+fn correct_clock_lengths() {
+    for &(value, secs) in &[("3:40", 220),
+                            ("0:00", 0),
+                            ("120:59", 7259),
+                            ("1:02:03", 3723),
+                            ("0:00:59", 59),
+                            ("220", 220),
+                            ("9999999999999999999:00", u64::MAX)] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), &ParseOptions { allow_clock_lengths: true, ..ParseOptions::default() }),
+                   Ok(vec![PlaylistElement {
+                               path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                               title: None,
+                               len: ElementLength::Seconds(secs),
+                               genre: None,
+                           }]),
+                   "{}",
+                   value);
+    }
+}
+
+#[test]
+fn correct_length_units() {
+    let opts = ParseOptions { allow_length_units: true, ..ParseOptions::default() };
+    for &(value, len, opts) in &[("420s", ElementLength::Seconds(420), &opts),
+                                 ("420 sec", ElementLength::Seconds(420), &opts),
+                                 ("420SEC", ElementLength::Seconds(420), &opts),
+                                 ("420 \tS", ElementLength::Seconds(420), &opts),
+                                 ("-1s", ElementLength::Unknown, &opts),
+                                 ("420", ElementLength::Seconds(420), &opts),
+                                 ("180.5s", ElementLength::Seconds(181), &ParseOptions { round_fractional_lengths: true, ..opts.clone() }),
+                                 ("3:40 sec", ElementLength::Seconds(220), &ParseOptions { allow_clock_lengths: true, ..opts.clone() })] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), opts).map(|e| e[0].len), Ok(len), "{}", value);
+    }
+}
+
+#[test]
+fn correct_negative_lengths() {
+    let opts = ParseOptions::default().negative_length_is_unknown(true);
+    for &(value, len) in &[("-1", ElementLength::Unknown),
+                           ("-2", ElementLength::Unknown),
+                           ("-100", ElementLength::Unknown),
+                           ("-99999999999999999999999", ElementLength::Unknown),
+                           ("124", ElementLength::Seconds(124))] {
+        let data = format!("[playlist]\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1={}\n\
+                            NumberOfEntries=1",
+                           value);
+        assert_eq!(parse_with(&mut data.as_bytes(), &opts).map(|e| e[0].len), Ok(len), "{}", value);
+    }
+
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 Length1=-2s\n\
+                 NumberOfEntries=1";
+    assert_eq!(parse_with(&mut &data[..], &opts.clone().allow_length_units(true)).map(|e| e[0].len), Ok(ElementLength::Unknown));
+}
+
+#[test]
+fn correct_clamped_length() {
+    assert_eq!(parse_with(&mut &b"[playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                                  Length1=99999999999999999999999\n\
+                                  File2=S:/M J U Z I K/pobrany/Stronger.mp3\n\
+                                  Length2=18446744073709551615\n\
+                                  NumberOfEntries=2"
+                                    [..],
+                          &ParseOptions { clamp_overflowing_lengths: true, ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(u64::MAX),
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(u64::MAX),
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_keys_before_section() {
+    let data = b"Version=2\n\
+                 NumberOfEntries=1\n\
+                 File2=S:/M J U Z I K/pobrany/Stronger.mp3\n\
+                 [playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 NumberOfEntries=2\n";
+
+    assert_eq!(parse_with(&mut &data[..],
+                          &ParseOptions { allow_keys_before_section: true, ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+    assert_eq!(parse_with(&mut &b"Version=2\n\
+                                  NumberOfEntries=1\n\
+                                  [playlist]\n\
+                                  File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n"[..],
+                          &ParseOptions { allow_keys_before_section: true, ..ParseOptions::default() }),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }]));
+}
+
+#[test]
+fn correct_visit() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 \n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 Length2=79\n\
+                 \n\
+                 Length3=Abolish the Burgeoisie!\n\
+                 \n\
+                 NumberOfEntries=3\n";
+
+    let mut visited = Vec::new();
+    assert_eq!(parse_visit(&mut &data[..], |e| {
+                   visited.push(e);
+                   if visited.len() == 2 {
+                       ControlFlow::Break(())
+                   } else {
+                       ControlFlow::Continue(())
+                   }
+               }),
+               Ok(()));
+    assert_eq!(visited, parse_iter(&mut &data[..]).unwrap().take(2).collect::<Result<Vec<_>, _>>().unwrap());
+
+    let mut visited = 0;
+    assert!(parse_visit(&mut &data[..], |_| {
+                visited += 1;
+                ControlFlow::Continue(())
+            })
+            .is_err());
+    assert_eq!(visited, 2);
+}
+
+#[test]
+fn correct_spaces_around_equals() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             Title1 =A-F-R-O & NGHTMRE - Stronger\n\
+                             Length1= 218\n\
+                             \tGenre1\t=\tTrap\t\n\
+                             \n\
+                             NumberOfEntries  =  1\n\
+                             Version = 2\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                           len: ElementLength::Seconds(218),
+                           genre: Some("Trap".to_string()),
+                       }]));
+}
+
+#[test]
+fn correct_skip_to_section() {
+    let expected = Ok(vec![PlaylistElement {
+                               path: "http://127.0.0.1:8002/stream".to_string(),
+                               title: Some("Unknown Radio".to_string()),
+                               len: ElementLength::Unknown,
+                               genre: None,
+                           }]);
+    for junk in &[&b""[..],
+                  &b"HTTP/1.1 200 OK\r\n\r\n"[..],
+                  &b"\x00\xFF\xFE garbage = [not a section\n"[..],
+                  &b"[playlist"[..],
+                  &b"[Unrelated]\nFile1=Not an entry\n"[..]] {
+        let mut data = junk.to_vec();
+        data.extend_from_slice(b"[Playlist]\n\
+                                 File1=http://127.0.0.1:8002/stream\n\
+                                 Title1=Unknown Radio\n\
+                                 NumberOfEntries=1\n");
+        assert_eq!(parse_with(&mut &data[..], &ParseOptions { skip_to_section: true, ..ParseOptions::default() }),
+                   expected,
+                   "{:?}",
+                   junk);
+    }
+}
+
+#[test]
+fn correct_name() {
+    let entries = vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       }];
+    for &(keys, name) in &[("", None),
+                           ("PlaylistName=A-F-R-O\n", Some("A-F-R-O")),
+                           ("Title=A-F-R-O\n", Some("A-F-R-O")),
+                           ("Title=Tales From The Basement\nPlaylistName=A-F-R-O\n", Some("A-F-R-O"))] {
+        let data = format!("[playlist]\n\
+                            {}\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Title1=A-F-R-O & NGHTMRE - Stronger\n\
+                            NumberOfEntries=1\n",
+                           keys);
+        assert_eq!(parse_full(&mut data.as_bytes(), &ParseOptions::default()),
+                   Ok(Playlist {
+                       name: name.map(str::to_string),
+                       entries: entries.clone(),
+                   }),
+                   "{:?}",
+                   keys);
+    }
+
+    let playlist = Playlist {
+        name: Some("A-F-R-O".to_string()),
+        entries,
+    };
+    let mut buf = Vec::new();
+    playlist.write(&mut buf).unwrap();
+    assert_eq!(parse_full(&mut &buf[..], &ParseOptions::default()), Ok(playlist.clone()));
+
+    let opts = pls::WriteOptions::default().line_ending(pls::LineEnding::CrLf).byte_order_mark(true).emit_version(false);
+    let mut crlf = Vec::new();
+    playlist.write_with(&opts, &mut crlf).unwrap();
+    assert_eq!(crlf, [&b"\xEF\xBB\xBF"[..], &String::from_utf8(buf).unwrap().replace("Version=2\n", "").replace('\n', "\r\n").into_bytes()].concat());
+    assert_eq!(parse_full(&mut &crlf[..], &ParseOptions::default()), Ok(playlist.clone()));
+    assert_eq!(playlist.write_with(&opts.error_on_empty(true), &mut Vec::new()).map_err(|e| e.kind()), Ok(()));
+    assert_eq!(Playlist { name: playlist.name, entries: vec![] }.write_with(&pls::WriteOptions::default().error_on_empty(true), &mut Vec::new())
+                   .map_err(|e| e.kind()),
+               Err(std::io::ErrorKind::InvalidInput));
+}
+
+#[test]
+fn correct_noncontiguous_numeric_order() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File10=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 Length10=124\n\
+                 File02=Not an entry\n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 Title3=Not an entry either\n\
+                 NumberOfEntries=3\n";
+    let opts = ParseOptions { require_contiguous_indices: false, ..ParseOptions::default() };
+    assert_eq!(parse_indexed(&mut &data[..], &opts),
+               Ok(vec![(1,
+                        PlaylistElement {
+                            path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                            title: None,
+                            len: ElementLength::Unknown,
+                            genre: None,
+                        }),
+                       (2,
+                        PlaylistElement {
+                            path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                            title: None,
+                            len: ElementLength::Unknown,
+                            genre: None,
+                        }),
+                       (10,
+                        PlaylistElement {
+                            path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                            title: None,
+                            len: ElementLength::Seconds(124),
+                            genre: None,
+                        })]));
+
+    assert_eq!(parse_with(&mut &data[..], &ParseOptions { allow_missing_file: true, ..opts.clone() }).unwrap().iter().map(|e| e.path_str()).collect::<Vec<_>>(),
+               vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                    "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3",
+                    "",
+                    "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3"]);
+}
+
+#[test]
+fn correct_live_stream_lengths() {
+    for &(scheme, live) in &[("rtsp", true), ("RTSP", true), ("mms", true), ("shout", true), ("http", false), ("file", false)] {
+        let data = format!("[playlist]\n\
+                            File1={}://127.0.0.1:8002/stream\n\
+                            Length1=218\n\
+                            File2={0}://127.0.0.1:8002/stream\n\
+                            Length2=Live!\n\
+                            NumberOfEntries=2\n",
+                           scheme);
+        let parsed = parse_with(&mut data.as_bytes(), &ParseOptions { ignore_live_stream_lengths: true, ..ParseOptions::default() });
+        if live {
+            let elems = parsed.unwrap();
+            assert_eq!(elems.iter().map(|e| e.len).collect::<Vec<_>>(), vec![ElementLength::Unknown; 2], "{}", scheme);
+            assert!(elems.iter().all(|e| e.is_url() && e.is_stream()), "{}", scheme);
+        } else {
+            assert!(parsed.is_err(), "{}", scheme);
+        }
+
+        let mut first_only = data.as_bytes()[..data.find("File2").unwrap()].to_vec();
+        first_only.extend_from_slice(b"NumberOfEntries=1\n");
+        assert_eq!(parse(&mut &first_only[..]).unwrap()[0].len, ElementLength::Seconds(218), "{}", scheme);
+    }
+}
+
+#[test]
+fn correct_count_larger_than_present() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 File4=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 Title4=A-F-R-O - Animal Kingdom\n\
+                 NumberOfEntries=5\n";
+    assert_eq!(parse(&mut &data[..]),
+               Err(pls::ParseError::MissingKey {
+                   key: "File3".to_string(),
+                   entry: Some(3),
+               }));
+
+    let opts = ParseOptions { require_contiguous_indices: false, ..ParseOptions::default() };
+    let iter = parse_iter_with(&mut &data[..], &opts).unwrap();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.missing_indices().collect::<Vec<_>>(), vec![3, 5]);
+    assert_eq!(iter.map(|e| e.unwrap().path).collect::<Vec<_>>(),
+               vec!["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3",
+                    "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3",
+                    "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3"]);
+    assert_eq!(parse_indexed(&mut &data[..], &opts).unwrap().iter().map(|e| e.0).collect::<Vec<_>>(), vec![1, 2, 4]);
+
+    let beyond = b"[playlist]\n\
+                   File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                   File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                   NumberOfEntries=1\n";
+    assert_eq!(parse(&mut &beyond[..]).unwrap().len(), 1);
+    let iter = parse_iter_with(&mut &beyond[..], &opts).unwrap();
+    assert_eq!(iter.missing_indices().count(), 0);
+    assert_eq!(iter.len(), 2);
+
+    let huge = b"[playlist]\n\
+                 File2=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File4=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 File18446744073709551615=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 NumberOfEntries=18446744073709551615\n";
+    let iter = parse_iter_with(&mut &huge[..], &opts).unwrap();
+    assert_eq!(iter.missing_indices().take(4).collect::<Vec<_>>(), vec![1, 3, 5, 6]);
+    assert_eq!(iter.len(), 3);
+
+    let huge = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 NumberOfEntries=18446744073709551615\n";
+    let iter = parse_iter_with(&mut &huge[..], &opts).unwrap();
+    assert_eq!(iter.missing_indices().take(3).collect::<Vec<_>>(), vec![2, 4, 5]);
+}
+
+#[test]
+fn correct_lenient() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File10=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 Length10=124\n\
+                 File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 Title3=A-F-R-O - Activated Trap Locks\n\
+                 Title2=Not an entry\n\
+                 NumberOfEntries=3\n";
+    assert_eq!(parse(&mut &data[..]),
+               Err(pls::ParseError::MissingKey {
+                   key: "File2".to_string(),
+                   entry: Some(2),
+               }));
+    assert_eq!(pls::parse_lenient(&mut &data[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                           title: Some("A-F-R-O - Activated Trap Locks".to_string()),
+                           len: ElementLength::Unknown,
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                           title: None,
+                           len: ElementLength::Seconds(124),
+                           genre: None,
+                       }]));
+
+    let contiguous = b"[playlist]\n\
+                       File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                       NumberOfEntries=1\n";
+    assert_eq!(pls::parse_lenient(&mut &contiguous[..]), parse(&mut &contiguous[..]));
+    assert_eq!(pls::parse_lenient(&mut &b"[playlist]\nFile1=Stronger.mp3\nFile3=Animal Kingdom.mp3\n"[..]).map(|e| pls::paths(&e).join("|")),
+               Ok("Stronger.mp3|Animal Kingdom.mp3".to_string()));
+    assert!(pls::parse_lenient(&mut &b"[playlist]\nFile1=Stronger.mp3\nNumberOfEntries=one\n"[..]).is_err());
+}
+
+#[test]
+fn correct_coalesced() {
+    let data = b"[playlist]\n\
+                 File1=http://127.0.0.1:8002/stream\n\
+                 Title1=Unknown Radio\n\
+                 File2=http://127.0.0.1:8002/stream\n\
+                 File3=http://127.0.0.1:8002/stream\n\
+                 Length3=218\n\
+                 File4=http://127.0.0.1:8002/backup\n\
+                 File5=http://127.0.0.1:8002/stream\n\
+                 NumberOfEntries=5\n";
+    let opts = ParseOptions { coalesce_adjacent_duplicates: true, ..ParseOptions::default() };
+
+    let elems = parse_with(&mut &data[..], &opts).unwrap();
+    assert_eq!(elems.iter().map(|e| e.path_str()).collect::<Vec<_>>(),
+               vec!["http://127.0.0.1:8002/stream", "http://127.0.0.1:8002/backup", "http://127.0.0.1:8002/stream"]);
+    assert_eq!(elems[0].title_str(), "Unknown Radio");
+    assert_eq!(elems[0].len, ElementLength::Unknown);
+
+    assert_eq!(parse_indexed(&mut &data[..], &opts).unwrap().iter().map(|e| e.0).collect::<Vec<_>>(), vec![1, 4, 5]);
+    assert_eq!(parse_full(&mut &data[..], &opts).unwrap().entries, elems);
+    assert_eq!(parse(&mut &data[..]).unwrap().len(), 5);
+}
+
+#[test]
+fn correct_nul_padded() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\0\0\0\0\n\
+                             Title1=A-F-R-O & NGHTMRE - Stronger \0\0\0\0\0\0\0\0\n\
+                             Length1=124\0\n\
+                             NumberOfEntries=1\0\0\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                           len: ElementLength::Seconds(124),
+                           genre: None,
+                       }]));
+    assert_eq!(parse(&mut &b"[playlist]\nFile1=\0Stronger.mp3\nNumberOfEntries=1\n"[..]).unwrap()[0].path, "\0Stronger.mp3");
+}
+
+#[test]
+fn correct_prefix() {
+    let playlist = "; Exported by Unknown Player\r\n\
+                    [Playlist]\r\n\
+                    File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                    Title1 = A-F-R-O & NGHTMRE - Stronger\r\n\
+                    \r\n\
+                    NumberOfEntries=1\r\n\
+                    Version=2\r\n";
+    for &(rest, consumed) in &[("", playlist.len()),
+                               ("\r\n; Next up\r\n", playlist.len()),
+                               ("[playlist]\nNumberOfEntries=0\n", playlist.len()),
+                               ("Unrelated=data\n", playlist.len()),
+                               ("Length1=124\nmore data", playlist.len() + "Length1=124\n".len())] {
+        let data = format!("{}{}", playlist, rest);
+        let (elems, len) = parse_prefix(data.as_bytes()).unwrap();
+        assert_eq!(len, consumed, "{:?}", rest);
+        assert_eq!(elems.len(), 1);
+        assert_eq!(elems[0].title_str(), "A-F-R-O & NGHTMRE - Stronger");
+    }
+
+    let mut data = playlist.as_bytes().to_vec();
+    data.extend(&[0xFF, 0xFE, b'\n', b'F', b'i', b'l', b'e', b'2', b'=']);
+    assert_eq!(parse_prefix(&data).unwrap().1, playlist.len());
+
+    assert_eq!(parse_prefix(b"File1=Track 1.mp3\n[playlist]\nNumberOfEntries=0\n"), Err(pls::ParseError::MissingPlaylistSection));
+}
+
+#[test]
+fn correct_prefix_bom() {
+    let data = b"\xEF\xBB\xBF[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 NumberOfEntries=1\n";
+    let (elems, len) = parse_prefix(&[&data[..], b"\x00\x01 container data"].concat()).unwrap();
+    assert_eq!(len, data.len());
+    assert_eq!(elems, parse(&mut &data[..]).unwrap());
+
+    assert_eq!(parse_prefix(b"\xEF\xBB\xBF[playlist]\nNumberOfEntries=0\n"), Ok((vec![], 3 + 29)));
+    assert_eq!(parse_prefix(b"\xEF\xBB\xBFFile1=Track 1.mp3\n"), Err(pls::ParseError::MissingPlaylistSection));
+}
+
+#[test]
+fn correct_backslashes() {
+    let data = b"[playlist]\r\n\
+                 File1=S:\\M J U Z I K\\pobrany\\A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                 Title1=A-F-R-O \\ NGHTMRE \\nStronger\r\n\
+                 File2=S:\\M J U Z I K\\pobrany\\\r\n\
+                 NumberOfEntries=2\r\n";
+    let elems = parse(&mut &data[..]).unwrap();
+    assert_eq!(elems[0].path, "S:\\M J U Z I K\\pobrany\\A-F-R-O & NGHTMRE - Stronger.mp3");
+    assert_eq!(elems[0].title_str(), "A-F-R-O \\ NGHTMRE \\nStronger");
+    assert_eq!(elems[1].path, "S:\\M J U Z I K\\pobrany\\");
+}
+
+#[test]
+fn correct_continuation_lines() {
+    let data = b"[playlist]\r\n\
+                 File1=S:\\M J U Z I K\\pobrany\\A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                 File2=http://127.0.0.1:8002/\\\r\n\
+                 stream?id=\\\n\
+                 1\r\n\
+                 NumberOfEntries=2\r\n";
+    let opts = ParseOptions { join_continuation_lines: true, ..ParseOptions::default() };
+    assert_eq!(parse_with(&mut &data[..], &opts).unwrap().iter().map(|e| e.path_str()).collect::<Vec<_>>(),
+               ["S:\\M J U Z I K\\pobrany\\A-F-R-O & NGHTMRE - Stronger.mp3", "http://127.0.0.1:8002/stream?id=1"]);
+}
+
+#[test]
+fn correct_tabs() {
+    let data = b"\t[playlist]\t\r\n\
+                 File1\t=\tS:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\t\r\n\
+                 \tTitle1 \t=\t A-F-R-O & NGHTMRE - Stronger\r\n\
+                 Length1\t=218\t\r\n\
+                 NumberOfEntries\t= \t1\r\n";
+    let expected = vec![PlaylistElement {
+                            path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                            title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                            len: ElementLength::Seconds(218),
+                            genre: None,
+                        }];
+    assert_eq!(parse(&mut &data[..]), Ok(expected.clone()));
+    assert_eq!(parse_prefix(&data[..]), Ok((expected, data.len())));
+    assert!(pls::is_valid_pls(data));
+}
+
+#[test]
+fn correct_version_presence() {
+    let without = b"[playlist]\n\
+                    File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                    NumberOfEntries=1\n";
+    let with = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 NumberOfEntries=1\n\
+                 Version=2\n";
+    let strict = ParseOptions { require_version: true, ..ParseOptions::default() };
+
+    assert_eq!(parse(&mut &without[..]).unwrap().len(), 1);
+    assert_eq!(parse(&mut &with[..]).unwrap().len(), 1);
+    assert_eq!(parse_with(&mut &with[..], &strict).unwrap().len(), 1);
+
+    let mut written = Vec::new();
+    pls::write(&parse(&mut &without[..]).unwrap(), &mut written).unwrap();
+    assert_eq!(parse_with(&mut &written[..], &strict).unwrap().len(), 1);
+}
+
+#[test]
+fn correct_key_order() {
+    assert_eq!(parse(&mut &b"[playlist]\n\
+                             NumberOfEntries=2\n\
+                             Length2=124\n\
+                             Title1=A-F-R-O & NGHTMRE - Stronger\n\
+                             Genre2=Trap\n\
+                             Length1=218\n\
+                             File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                             Version=2\n\
+                             File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                             Title2=A-F-R-O - Animal Kingdom\n"[..]),
+               Ok(vec![PlaylistElement {
+                           path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                           title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                           len: ElementLength::Seconds(218),
+                           genre: None,
+                       },
+                       PlaylistElement {
+                           path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                           title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                           len: ElementLength::Seconds(124),
+                           genre: Some("Trap".to_string()),
+                       }]));
+}
+
+#[test]
+fn correct_multi() {
+    let data = b"Version=2\n\
+                 [playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 Title1=A-F-R-O & NGHTMRE - Stronger\n\
+                 NumberOfEntries=1\n\
+                 \n\
+                 [Unrelated]\n\
+                 File2=Not an entry\n\
+                 \n\
+                 [PLAYLIST]\n\
+                 File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 Length2=124\n\
+                 NumberOfEntries=2\n\
+                 Version=2\n\
+                 [playlist]\n\
+                 NumberOfEntries=0\n";
+    let playlists = parse_multi(&mut &data[..]).unwrap();
+    assert_eq!(playlists.iter().map(Vec::len).collect::<Vec<_>>(), [1, 2, 0]);
+    assert_eq!(playlists[0][0].title_str(), "A-F-R-O & NGHTMRE - Stronger");
+    assert_eq!(playlists[1][0].path, "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3");
+    assert_eq!(playlists[1][1].len, ElementLength::Seconds(124));
+
+    assert_eq!(parse_multi(&mut &b"[playlist]\nNumberOfEntries=0\n"[..]), Ok(vec![vec![]]));
+    assert_eq!(parse_multi(&mut &b""[..]), Err(pls::ParseError::Empty));
+    assert_eq!(parse_multi(&mut &b"File1=Stronger.mp3\n"[..]), Err(pls::ParseError::MissingPlaylistSection));
+    assert_eq!(parse_multi(&mut &b"[playlist]\nNumberOfEntries=0\n[playlist]\nFile1=Stronger.mp3\n"[..]),
+               Err(pls::ParseError::MissingKey {
+                   key: "NumberOfEntries|numberofentries|NumberOfEvents".to_string(),
+                   entry: None,
+               }));
+}
+
+#[test]
+fn correct_multi_bom() {
+    let data = b"\xEF\xBB\xBF[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 NumberOfEntries=1\n\
+                 [playlist]\n\
+                 File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                 NumberOfEntries=1\n";
+    assert_eq!(parse_multi(&mut &data[..]).unwrap().iter().map(|p| pls::paths(p)).collect::<Vec<_>>(),
+               [["S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3"],
+                ["S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3"]]);
+    assert_eq!(parse_multi(&mut &b"\xEF\xBB\xBF[playlist]\nNumberOfEntries=0\n"[..]), Ok(vec![vec![]]));
+}
+
+#[test]
+fn correct_options_builder() {
+    let opts = ParseOptions::default()
+        .allow_missing_file(true)
+        .section_name("radio")
+        .require_contiguous_indices(false)
+        .max_bytes(1024);
+    assert_eq!(opts,
+               ParseOptions {
+                   allow_missing_file: true,
+                   section_name: Some("radio".to_string()),
+                   require_contiguous_indices: false,
+                   max_bytes: Some(1024),
+                   ..ParseOptions::default()
+               });
+    assert_eq!(ParseOptions::default().require_contiguous_indices(true), ParseOptions::default());
+
+    let data = b"\xEF\xBB\xBF[Radio]\n\
+                 Title2=Unknown Radio\n\
+                 NumberOfEntries=1\n";
+    assert_eq!(parse_with(&mut &data[..], &opts).unwrap().iter().map(|e| e.title_str()).collect::<Vec<_>>(), ["Unknown Radio"]);
+    assert_eq!(parse_with(&mut &b"\xEF\xBB\xBF[playlist]\nNumberOfEntries=0\n"[..], &ParseOptions::default()), Ok(vec![]));
+    assert_eq!(parse_with(&mut &b"\xEF\xBB\xBF\n"[..], &ParseOptions::default()), Err(pls::ParseError::Empty));
+}
+
+#[test]
+fn correct_without_count() {
+    let data = b"[playlist]\n\
+                 File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                 File3=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3\n\
+                 Length3=218\n";
+    let opts = ParseOptions::default().require_number_of_entries(false);
+    let iter = parse_iter_with(&mut &data[..], &opts).unwrap();
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.missing_indices().collect::<Vec<_>>(), vec![2]);
+    assert_eq!(parse_with(&mut &data[..], &opts).unwrap().iter().map(|e| e.len).collect::<Vec<_>>(),
+               vec![ElementLength::Unknown, ElementLength::Seconds(218)]);
+    assert_eq!(parse_with(&mut &b"[playlist]\nVersion=2\n"[..], &opts), Ok(vec![]));
+
+    // A count that's there is still used
+    let counted = [&data[..], b"NumberOfEntries=1\n"].concat();
+    assert_eq!(parse_with(&mut &counted[..], &opts).map(|e| e.len()), Ok(1));
+    assert_eq!(parse_with(&mut &counted[..], &opts.clone().require_contiguous_indices(false)).map(|e| e.len()), Ok(2));
+    assert!(parse_with(&mut &b"[playlist]\nNumberOfEntries=one\n"[..], &opts).is_err());
+}
+
+#[test]
+fn correct_allowed_versions() {
+    let data = |v: &str| format!("[playlist]\nFile1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\nNumberOfEntries=1\nVersion={}\n", v);
+    let opts = ParseOptions::default().allowed_versions(Some(vec![1, 2, 3]));
+    for v in &["1", "2", "3"] {
+        assert_eq!(parse_with(&mut data(v).as_bytes(), &opts).map(|e| e.len()), Ok(1), "{}", v);
+    }
+    assert_eq!(parse_with(&mut data("4").as_bytes(), &opts), Err(pls::ParseError::InvalidVersion(4)));
+    assert_eq!(parse_with(&mut data("2").as_bytes(), &opts.clone().allowed_versions(Some(vec![]))), Err(pls::ParseError::InvalidVersion(2)));
+
+    let any = ParseOptions::default().allowed_versions(None);
+    assert_eq!(parse_with(&mut data("999").as_bytes(), &any).map(|e| e.len()), Ok(1));
+    assert!(parse_with(&mut data("two").as_bytes(), &any).is_err());
+    assert_eq!(ParseOptions::default().allowed_versions(Some(vec![2])), ParseOptions::default());
+}
+
+#[test]
+fn correct_first_url() {
+    let radio = "[playlist]\n\
+                 numberofentries=2\n\
+                 File1=http://127.0.0.1:8002/stream\n\
+                 Title1=(#1 - 1/420) Radio\n\
+                 Length1=-1\n\
+                 File2=http://127.0.0.1:8003/stream\n\
+                 Length2=-1\n\
+                 Version=2\n";
+    assert_eq!(pls::first_url(radio.as_bytes()), Ok(Some("http://127.0.0.1:8002/stream".to_string())));
+    assert_eq!(pls::first_url(&utf16(radio, [0xFF, 0xFE], u16::to_le_bytes)), Ok(Some("http://127.0.0.1:8002/stream".to_string())));
+
+    assert_eq!(pls::first_url(b"[playlist]\nFile1=Track 1.mp3\nFile2=http://127.0.0.1:8002/stream\nNumberOfEntries=2\n"), Ok(None));
+    assert_eq!(pls::first_url(b"[playlist]\nNumberOfEntries=0\n"), Ok(None));
+    assert_eq!(pls::first_url(b""), Err(pls::ParseError::Empty));
+}
+
+#[test]
+fn correct_ref_keys() {
+    let data = b"[playlist]\n\
+                 Ref1=http://127.0.0.1:8002/stream\n\
+                 Title1=Unknown Radio\n\
+                 File2=S:/M J U Z I K/pobrany/Stronger.mp3\n\
+                 Ref2=S:/M J U Z I K/pobrany/Broken.mp3\n\
+                 HREF3=http://127.0.0.1:8003/stream\n\
+                 Ref4=http://127.0.0.1:8004/stream\n\
+                 HREF4=http://127.0.0.1:8005/stream\n\
+                 Ref05=http://127.0.0.1:8006/stream\n\
+                 NumberOfEntries=4\n";
+    let opts = ParseOptions::default().allow_ref_keys(true);
+
+    assert_eq!(parse_with(&mut &data[..], &opts).unwrap().iter().map(|e| &e.path[..]).collect::<Vec<_>>(),
+               ["http://127.0.0.1:8002/stream", "S:/M J U Z I K/pobrany/Stronger.mp3", "http://127.0.0.1:8003/stream", "http://127.0.0.1:8004/stream"]);
+    assert_eq!(parse_with(&mut &data[..], &ParseOptions { require_contiguous_indices: false, ..opts.clone() }).unwrap().len(), 4);
+    assert_eq!(parse(&mut &data[..]),
+               Err(pls::ParseError::MissingKey {
+                   key: "File1".to_string(),
+                   entry: Some(1),
+               }));
+}
+
+#[test]
+fn correct_header_trailer() {
+    let expected = vec![PlaylistElement {
+                            path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                            title: None,
+                            len: ElementLength::Seconds(230),
+                            genre: None,
+                        }];
+    for header in &["[playlist]   ", "[playlist] ;note", "[playlist]\t# exported by Unknown Player", "[ Playlist ] ; note"] {
+        let data = format!("{}\n\
+                            File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                            Length1=230\n\
+                            NumberOfEntries=1\n",
+                           header);
+        assert_eq!(parse(&mut data.as_bytes()).as_ref(), Ok(&expected), "{:?}", header);
+        assert_eq!(parse_prefix(data.as_bytes()), Ok((expected.clone(), data.len())), "{:?}", header);
+        assert_eq!(parse_multi(&mut format!("{}\n{}", data, data).as_bytes()), Ok(vec![expected.clone(), expected.clone()]), "{:?}", header);
+    }
+
+    assert_eq!(parse_prefix(b"[playlist] File1=Stronger.mp3\nNumberOfEntries=1\n"), Err(pls::ParseError::MissingPlaylistSection));
+}
+
+#[test]
+fn correct_options_everywhere() {
+    let data = b"[playlist]\n\
+                 File1=http://127.0.0.1:8002/stream\n\
+                 File2=http://127.0.0.1:8002/stream\n\
+                 NumberOfEntries=2\n\
+                 Version=3\n";
+    let opts = ParseOptions::default().allowed_versions(None).coalesce_adjacent_duplicates(true);
+    let expected = vec!["http://127.0.0.1:8002/stream"];
+    let utf16: Vec<u8> = str::from_utf8(data).unwrap().encode_utf16().fold(vec![0xFE, 0xFF], |mut acc, c| {
+        acc.extend_from_slice(&c.to_be_bytes());
+        acc
+    });
+
+    assert!(parse_visit(&mut &data[..], |_| ControlFlow::Continue(())).is_err());
+    let mut visited = vec![];
+    pls::parse_visit_with(&mut &data[..], &opts, |e| {
+            visited.push(e);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+    assert_eq!(pls::paths(&visited), expected);
+
+    assert!(parse_str(str::from_utf8(data).unwrap()).is_err());
+    assert_eq!(pls::paths(&pls::parse_str_with(str::from_utf8(data).unwrap(), &opts).unwrap()), expected);
+
+    assert!(parse_bytes(&utf16).is_err());
+    assert_eq!(pls::paths(&pls::parse_bytes_with(&utf16, &opts).unwrap()), expected);
+    assert_eq!(pls::parse_bytes_with(&utf16, &ParseOptions { max_bytes: Some(utf16.len() - 1), ..opts.clone() }),
+               Err(pls::ParseError::TooLarge(utf16.len() - 1)));
+    assert_eq!(pls::paths(&pls::parse_bytes_with(&utf16, &ParseOptions { max_bytes: Some(utf16.len()), ..opts.clone() }).unwrap()),
+               expected);
+
+    assert!(pls::first_url(data).is_err());
+    assert_eq!(pls::first_url_with(data, &opts).unwrap(), Some(expected[0].to_string()));
+
+    assert_eq!(parse_concat(vec![&data[..], &data[..]]).map_err(|e| e.0), Err(0));
+    assert_eq!(pls::paths(&pls::parse_concat_with(vec![&data[..], &data[..]], &opts).unwrap()), [expected[0], expected[0]]);
+
+    let multi = [&data[..], &data[..]].concat();
+    assert!(parse_multi(&mut &multi[..]).is_err());
+    assert_eq!(pls::parse_multi_with(&mut &multi[..], &opts).unwrap().iter().map(|p| pls::paths(p)).collect::<Vec<_>>(),
+               [expected.clone(), expected.clone()]);
+    assert_eq!(pls::parse_multi_with(&mut &multi[..], &ParseOptions { max_bytes: Some(data.len()), ..opts.clone() }),
+               Err(pls::ParseError::TooLarge(data.len())));
+
+    assert!(parse_prefix(&multi).is_err());
+    let (elems, len) = pls::parse_prefix_with(&multi, &opts).unwrap();
+    assert_eq!((pls::paths(&elems), len), (expected.clone(), data.len()));
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn correct_options_encoding() {
+    let data = b"[playlist]\n\
+                 File1=Caf\xE9.mp3\n\
+                 Version=3\n";
+    let opts = ParseOptions::default().allowed_versions(None).require_number_of_entries(false);
+    assert!(pls::parse_with_encoding(&mut &data[..], encoding_rs::WINDOWS_1252).is_err());
+    assert_eq!(pls::paths(&pls::parse_with_encoding_and_options(&mut &data[..], encoding_rs::WINDOWS_1252, &opts).unwrap()), ["Café.mp3"]);
+    assert_eq!(pls::parse_with_encoding_and_options(&mut &data[..], encoding_rs::WINDOWS_1252, &opts.clone().max_bytes(data.len() - 1)),
+               Err(pls::ParseError::TooLarge(data.len() - 1)));
+}
@@ -0,0 +1,1055 @@
+use pls::{self, PlaylistElement, PlaylistElementRef, ElementLength, EntryKey, LengthFormat, LineEnding, ParseOptions, WriteOptions, Writer, write_header,
+          write_header_with, write_entry, write_entry_with, write_footer, write_footer_with};
+use std::collections::BTreeMap;
+use std::{env, fs, io, process, thread};
+
+
+#[test]
+fn by_hand() {
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                     title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                     len: ElementLength::Seconds(124),
+                     genre: None,
+                 }];
+
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+
+    let mut by_hand = Vec::new();
+    write_header(&mut by_hand).unwrap();
+    for (i, elem) in elems.iter().enumerate() {
+        write_entry(i as u64 + 1, elem, &mut by_hand).unwrap();
+    }
+    write_footer(elems.len() as u64, &mut by_hand).unwrap();
+
+    assert_eq!(String::from_utf8(by_hand).unwrap(), String::from_utf8(whole).unwrap());
+
+    for opts in &[WriteOptions::default().line_ending(LineEnding::CrLf).byte_order_mark(true),
+                  WriteOptions::default().blank_line_between_entries(false).emit_version(false)] {
+        let mut by_hand = Vec::new();
+        write_header_with(opts, &mut by_hand).unwrap();
+        for (i, elem) in elems.iter().enumerate() {
+            write_entry_with(i as u64 + 1, elem, opts, &mut by_hand).unwrap();
+        }
+        write_footer_with(elems.len() as u64, opts, &mut by_hand).unwrap();
+
+        assert_eq!(String::from_utf8(by_hand).unwrap(), String::from_utf8(pls::to_bytes_with(&elems, opts)).unwrap());
+    }
+}
+
+#[test]
+fn append_file() {
+    let dir = env::temp_dir().join(format!("pls-rs-append_file-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("Favourites.pls");
+
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/01 Activated Trap Locks.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Seconds(79),
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                     title: Some("A-F-R-O - Animal Kingdom".to_string()),
+                     len: ElementLength::Seconds(124),
+                     genre: None,
+                 }];
+
+    pls::append_file(&path, &elems[..1]).unwrap();
+
+    // Fails after writing the first entry, which mustn't lose it
+    let before = fs::read(&path).unwrap();
+    let overflowing = WriteOptions::default().start_index(u64::MAX);
+    assert_eq!(pls::append_file_with(&path, &elems[1..], &overflowing).unwrap_err(), pls::ParseError::Io(io::ErrorKind::InvalidInput));
+    assert_eq!(fs::read(&path).unwrap(), before);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    pls::append_file(&path, &elems[1..]).unwrap();
+
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), whole);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn genre() {
+    let elems = [PlaylistElement {
+                     path: "http://127.0.0.1:8002/stream".to_string(),
+                     title: Some("Unknown Radio".to_string()),
+                     len: ElementLength::Unknown,
+                     genre: Some("Electronic Dance".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Seconds(124),
+                     genre: Some("Trap".to_string()),
+                 }];
+
+    let mut buf = Vec::new();
+    pls::write(&elems, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf.clone()).unwrap(),
+               "[playlist]\n\
+                File1=http://127.0.0.1:8002/stream\n\
+                Title1=Unknown Radio\n\
+                Genre1=Electronic Dance\n\
+                \n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                Length2=124\n\
+                Genre2=Trap\n\
+                \n\
+                NumberOfEntries=2\n\
+                Version=2\n");
+    assert_eq!(pls::parse(&mut &buf[..]), Ok(elems.to_vec()));
+}
+
+#[test]
+fn counted() {
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                     title: Some("A-F-R-O — Animal Kingdom".to_string()),
+                     len: ElementLength::Seconds(124),
+                     genre: None,
+                 }];
+
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+
+    let mut counted = Vec::new();
+    assert_eq!(pls::write_counted(&elems, &mut counted).unwrap(), whole.len());
+    assert_eq!(counted, whole);
+
+    assert_eq!(pls::write_counted(&[] as &[PlaylistElement], &mut Vec::new()).unwrap(), "[playlist]\nNumberOfEntries=0\nVersion=2\n".len());
+
+    for opts in &[WriteOptions::default().line_ending(LineEnding::CrLf).start_index(0).key_order([EntryKey::Length; 4]),
+                  WriteOptions::default().byte_order_mark(true).emit_version(false).align_keys(true)] {
+        let whole = pls::to_bytes_with(&elems, opts);
+        let mut counted = Vec::new();
+        assert_eq!(pls::write_counted_with(&elems, opts, &mut counted).unwrap(), whole.len());
+        assert_eq!(counted, whole);
+    }
+    assert_eq!(pls::write_counted_with(&[] as &[PlaylistElement], &WriteOptions::default().error_on_empty(true), &mut Vec::new()).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn encoding() {
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3".to_string(),
+                     title: Some("A-F-R-O — Animal Kingdom (Crème Brûlée Remix)".to_string()),
+                     len: ElementLength::Seconds(124),
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "S:/M J U Z I K/Z płyt/A-F-R-O - Tales From The Basement/03 CODE 829.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+
+    let mut buf = Vec::new();
+    pls::write_with_encoding(&elems, encoding_rs::WINDOWS_1252, true, &mut buf).unwrap();
+    assert_eq!(buf,
+               &b"[playlist]\n\
+                  File1=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                  Title1=A-F-R-O \x97 Animal Kingdom (Cr\xE8me Br\xFBl\xE9e Remix)\n\
+                  Length1=124\n\
+                  \n\
+                  File2=S:/M J U Z I K/Z p?yt/A-F-R-O - Tales From The Basement/03 CODE 829.mp3\n\
+                  \n\
+                  NumberOfEntries=2\n\
+                  Version=2\n"[..]);
+    assert_eq!(pls::parse_with_encoding(&mut &buf[..], encoding_rs::WINDOWS_1252).unwrap()[0], elems[0]);
+
+    let mut buf = Vec::new();
+    assert_eq!(pls::write_with_encoding(&elems, encoding_rs::WINDOWS_1252, false, &mut buf).unwrap_err().kind(),
+               io::ErrorKind::InvalidData);
+    assert_eq!(buf, vec![]);
+
+    let mut buf = b"\xEF\xBB\xBF".to_vec();
+    pls::write_with_encoding(&elems, encoding_rs::UTF_8, false, &mut buf).unwrap();
+    assert_eq!(pls::parse_with_encoding(&mut &buf[..], encoding_rs::WINDOWS_1252), Ok(elems.to_vec()));
+}
+
+#[test]
+fn empty() {
+    let mut buf = Vec::new();
+    pls::write_with(&[] as &[PlaylistElement], &WriteOptions::default(), &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(),
+               "[playlist]\n\
+                NumberOfEntries=0\n\
+                Version=2\n");
+
+    let mut buf = Vec::new();
+    let err = pls::write_with(&[] as &[PlaylistElement], &WriteOptions { error_on_empty: true, ..WriteOptions::default() }, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(buf, vec![]);
+
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &WriteOptions { error_on_empty: true, ..WriteOptions::default() }, &mut buf).unwrap();
+    assert_eq!(buf, whole);
+}
+
+#[test]
+fn borrowed() {
+    let owned = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                     len: ElementLength::Seconds(218),
+                     genre: Some("Trap".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "http://127.0.0.1:8002/stream".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let borrowed: Vec<_> = owned.iter()
+        .map(|e| {
+            PlaylistElementRef {
+                path: &e.path,
+                title: e.title.as_ref().map(|t| &t[..]),
+                len: e.len,
+                genre: e.genre.as_ref().map(|g| &g[..]),
+            }
+        })
+        .collect();
+
+    let mut whole = Vec::new();
+    pls::write(&owned, &mut whole).unwrap();
+    let mut buf = Vec::new();
+    pls::write(&borrowed, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), String::from_utf8(whole).unwrap());
+
+    assert_eq!(borrowed.into_iter().map(PlaylistElement::from).collect::<Vec<_>>(), owned);
+}
+
+#[test]
+fn valid() {
+    let elems: Vec<_> = (0..12)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: if i % 3 == 0 { Some(format!("A-F-R-O - {}", i)) } else { None },
+                len: if i % 2 == 0 { ElementLength::Seconds(i) } else { ElementLength::Unknown },
+                genre: None,
+            }
+        })
+        .collect();
+    for n in 0..elems.len() {
+        let mut buf = Vec::new();
+        pls::write(&elems[..n], &mut buf).unwrap();
+        assert!(pls::is_valid_pls(&buf), "{}", n);
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn arbitrary_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    // Deterministic, so failures reproduce
+    let mut state = 0x2545F4914F6CDD1Du64;
+    for _ in 0..256 {
+        let data: Vec<u8> = (0..512)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        pls::assert_round_trip(&Vec::<PlaylistElement>::arbitrary(&mut Unstructured::new(&data)).unwrap());
+    }
+}
+
+#[test]
+fn aligned() {
+    let elems: Vec<_> = (0..10)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: if i % 3 == 0 { Some(format!("A-F-R-O - {}", i)) } else { None },
+                len: if i % 2 == 0 { ElementLength::Seconds(i) } else { ElementLength::Unknown },
+                genre: if i == 9 { Some("Trap".to_string()) } else { None },
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &WriteOptions { align_keys: true, ..WriteOptions::default() }, &mut buf).unwrap();
+    let written = String::from_utf8(buf).unwrap();
+    assert!(written.starts_with("[playlist]\n\
+                                 File1  =S:/M J U Z I K/pobrany/00.mp3\n\
+                                 Title1 =A-F-R-O - 0\n\
+                                 Length1=0\n\
+                                 \n\
+                                 File2  =S:/M J U Z I K/pobrany/01.mp3\n\
+                                 \n"),
+            "{}",
+            written);
+    assert!(written.ends_with("File10  =S:/M J U Z I K/pobrany/09.mp3\n\
+                               Title10 =A-F-R-O - 9\n\
+                               Genre10 =Trap\n\
+                               \n\
+                               NumberOfEntries=10\n\
+                               Version=2\n"),
+            "{}",
+            written);
+
+    assert_eq!(pls::parse(&mut written.as_bytes()), Ok(elems));
+}
+
+#[test]
+fn range() {
+    let elems: Vec<_> = (0..20)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: None,
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+
+    for &(start, end) in &[(0, 20), (9, 20), (0, 0), (20, 20), (5, 6)] {
+        let mut buf = Vec::new();
+        pls::write_range(&elems, start..end, &mut buf).unwrap();
+        assert_eq!(pls::parse(&mut &buf[..]).unwrap(), &elems[start..end]);
+    }
+
+    for &(start, end) in &[(0, 21), (20, 21), (21, 21), (6, 5)] {
+        let mut buf = Vec::new();
+        assert_eq!(pls::write_range(&elems, start..end, &mut buf).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}..{}", start, end);
+        assert_eq!(buf, vec![]);
+    }
+}
+
+#[test]
+fn filtered() {
+    let elems: Vec<_> = (0..20)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: None,
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write_filtered(&elems, |e| e.len.seconds().unwrap_or(0) % 3 == 0, &mut buf).unwrap();
+    let written = pls::parse_indexed(&mut &buf[..], &Default::default()).unwrap();
+    assert_eq!(written.iter().map(|e| e.0).collect::<Vec<_>>(), (1..8).collect::<Vec<_>>());
+    assert_eq!(written.into_iter().map(|e| e.1).collect::<Vec<_>>(), elems.iter().step_by(3).cloned().collect::<Vec<_>>());
+
+    let mut buf = Vec::new();
+    pls::write_filtered(&elems, |_| false, &mut buf).unwrap();
+    assert_eq!(buf, b"[playlist]\nNumberOfEntries=0\nVersion=2\n");
+}
+
+#[test]
+fn writer() {
+    let elems: Vec<_> = (0..20)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: Some(format!("{:02}", i)),
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+
+    let mut writer = Writer::new(Vec::new());
+    writer.push(&elems[0]).unwrap();
+    writer.extend(&elems[1..10]);
+    let borrowed: Vec<_> = elems[10..]
+        .iter()
+        .map(|e| {
+            PlaylistElementRef {
+                path: &e.path,
+                title: e.title.as_ref().map(|t| &t[..]),
+                len: e.len,
+                genre: None,
+            }
+        })
+        .collect();
+    writer.push(&borrowed[0]).unwrap();
+    writer.extend(&borrowed[1..]);
+    assert_eq!(writer.count(), 20);
+    assert_eq!(writer.finish().unwrap(), whole);
+
+    assert_eq!(Writer::new(Vec::new()).finish().unwrap(), b"[playlist]\nNumberOfEntries=0\nVersion=2\n");
+    let err = Writer::with_options(Vec::new(), WriteOptions { error_on_empty: true, ..WriteOptions::default() }).finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut buf = [0u8; 100];
+    let mut writer = Writer::new(&mut buf[..]);
+    writer.extend(&elems);
+    writer.extend(&elems);
+    assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::WriteZero);
+    assert_eq!(&buf[..], &whole[..100]);
+}
+
+#[test]
+fn clock_lengths() {
+    let elems: Vec<_> = [0, 59, 60, 220, 3599, 3600, 3723, 86400 * 2 + 1, u64::MAX]
+        .iter()
+        .map(|&s| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{}.mp3", s),
+                title: None,
+                len: ElementLength::Seconds(s),
+                genre: None,
+            }
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &WriteOptions { length_format: LengthFormat::Clock, ..WriteOptions::default() }, &mut buf).unwrap();
+    let lengths: Vec<_> = String::from_utf8(buf.clone()).unwrap().lines().filter(|l| l.starts_with("Length")).map(str::to_string).collect();
+    assert_eq!(lengths,
+               ["Length1=0:00",
+                "Length2=0:59",
+                "Length3=1:00",
+                "Length4=3:40",
+                "Length5=59:59",
+                "Length6=1:00:00",
+                "Length7=1:02:03",
+                "Length8=48:00:01",
+                "Length9=5124095576030431:00:15"]);
+    assert_eq!(pls::parse_with(&mut &buf[..], &ParseOptions { allow_clock_lengths: true, ..ParseOptions::default() }).unwrap(), elems);
+
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &WriteOptions { length_format: LengthFormat::Seconds, ..WriteOptions::default() }, &mut buf).unwrap();
+    let mut default = Vec::new();
+    pls::write(&elems, &mut default).unwrap();
+    assert_eq!(buf, default);
+}
+
+#[test]
+fn canonical() {
+    let messy = b"; Exported by Unknown Player\r\n\
+                  [Playlist]\r\n\
+                  File1 = S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                  Title1=A-F-R-O   &  NGHTMRE - Stronger\r\n\
+                  Genre1=\r\n\
+                  Length1=0218\r\n\
+                  \r\n\
+                  \r\n\
+                  Title2=A-F-R-O - Animal Kingdom\r\n\
+                  File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\r\n\
+                  Genre2 :  Trap\r\n\
+                  Version=2\r\n\
+                  NumberOfEntries=2\r\n";
+    let canonical = |elems: &[PlaylistElement]| {
+        let mut buf = Vec::new();
+        pls::write_canonical(elems, &mut buf).unwrap();
+        buf
+    };
+
+    let once = canonical(&pls::parse(&mut &messy[..]).unwrap());
+    assert_eq!(String::from_utf8(once.clone()).unwrap(),
+               "[playlist]\n\
+                File1=S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3\n\
+                Title1=A-F-R-O & NGHTMRE - Stronger\n\
+                Length1=218\n\
+                \n\
+                File2=S:/M J U Z I K/Z plyt/A-F-R-O - Tales From The Basement/02 Animal Kingdom.mp3\n\
+                Title2=A-F-R-O - Animal Kingdom\n\
+                Genre2=Trap\n\
+                \n\
+                NumberOfEntries=2\n\
+                Version=2\n");
+    assert_eq!(canonical(&pls::parse(&mut &once[..]).unwrap()), once);
+
+    let elems = [PlaylistElement {
+                     path: "http://127.0.0.1:8002/stream".to_string(),
+                     title: Some("\tUnknown\r\nRadio  ".to_string()),
+                     len: ElementLength::Unknown,
+                     genre: Some("\n".to_string()),
+                 }];
+    let once = canonical(&elems);
+    assert_eq!(once, b"[playlist]\nFile1=http://127.0.0.1:8002/stream\nTitle1=Unknown Radio\n\nNumberOfEntries=1\nVersion=2\n");
+    assert_eq!(canonical(&pls::parse(&mut &once[..]).unwrap()), once);
+}
+
+#[test]
+fn to_path() {
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                     len: ElementLength::Seconds(218),
+                     genre: None,
+                 }];
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+
+    for ext in &["pls", "PLS"] {
+        let path = env::temp_dir().join(format!("pls-rs-to_path-{}.{}", process::id(), ext));
+        pls::write_to_path(&elems, &path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), whole);
+        fs::remove_file(&path).unwrap();
+    }
+
+    let mut m3u = Vec::new();
+    pls::write_m3u(&elems, &mut m3u).unwrap();
+    for ext in &["m3u", "M3U8"] {
+        let path = env::temp_dir().join(format!("pls-rs-to_path-{}.{}", process::id(), ext));
+        pls::write_to_path(&elems, &path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), m3u);
+        fs::remove_file(&path).unwrap();
+    }
+
+    for suffix in &[".xspf", ".pls.txt", "_pls", ".m3u.txt"] {
+        let path = env::temp_dir().join(format!("pls-rs-to_path-{}{}", process::id(), suffix));
+        assert_eq!(pls::write_to_path(&elems, &path).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}", path.display());
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+fn brackets() {
+    let elems = [PlaylistElement {
+                     path: "[Live]/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: Some("[Live] A-F-R-O & NGHTMRE - Stronger".to_string()),
+                     len: ElementLength::Seconds(218),
+                     genre: Some("[playlist]".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "http://127.0.0.1:8002/stream".to_string(),
+                     title: Some("[".to_string()),
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+
+    let mut buf = Vec::new();
+    pls::write(&elems, &mut buf).unwrap();
+    assert!(String::from_utf8(buf.clone()).unwrap().lines().filter(|l| l.starts_with('[')).eq(["[playlist]"].iter().cloned()));
+    assert_eq!(pls::parse(&mut &buf[..]).unwrap(), elems);
+    assert_eq!(pls::parse_multi(&mut &buf[..]).unwrap(), [elems.to_vec()]);
+    assert_eq!(pls::parse_prefix(&buf).unwrap(), (elems.to_vec(), buf.len()));
+}
+
+#[test]
+fn file_atomic() {
+    let dir = env::temp_dir().join(format!("pls-rs-file_atomic-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("Now Playing.pls");
+
+    let elems: Vec<_> = (0..20)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: None,
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+    for elems in &[&elems[..], &elems[..5], &[]] {
+        pls::write_file_atomic(*elems, &path).unwrap();
+
+        let mut whole = Vec::new();
+        pls::write(*elems, &mut whole).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), whole);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    assert!(pls::write_file_atomic(&elems, dir.join("nonexistent").join("Now Playing.pls")).is_err());
+    assert_eq!(pls::write_file_atomic(&elems, "/").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    // Fails after the temporary file's been written to
+    let before = fs::read(&path).unwrap();
+    assert_eq!(pls::write_file_atomic_with(&elems, &path, &WriteOptions::default().start_index(u64::MAX)).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read(&path).unwrap(), before);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_atomic_concurrent() {
+    let dir = env::temp_dir().join(format!("pls-rs-file_atomic_concurrent-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("Now Playing.pls");
+
+    let playlists: Vec<Vec<_>> = (0..8)
+        .map(|t| {
+            (0..500)
+                .map(|i| {
+                    PlaylistElement {
+                        path: format!("S:/M J U Z I K/pobrany/{}/{:03}.mp3", t, i),
+                        title: None,
+                        len: ElementLength::Seconds(i),
+                        genre: None,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let threads: Vec<_> = playlists.iter()
+        .cloned()
+        .map(|elems| {
+            let path = path.clone();
+            thread::spawn(move || for _ in 0..5 {
+                pls::write_file_atomic(&elems, &path).unwrap();
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // Whole, and from one writer
+    let written = pls::parse(&mut &fs::read(&path).unwrap()[..]).unwrap();
+    assert!(playlists.contains(&written));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn file_atomic_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = env::temp_dir().join(format!("pls-rs-file_atomic_permissions-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("Now Playing.pls");
+
+    fs::write(&path, "").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o604)).unwrap();
+    pls::write_file_atomic(&[] as &[PlaylistElement], &path).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o604);
+    assert_eq!(pls::parse(&mut &fs::read(&path).unwrap()[..]), Ok(vec![]));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn default_options() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: Some("Unknown Artist - Track 1".to_string()),
+                     len: ElementLength::Seconds(420),
+                     genre: Some("Rock".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "http://somestream.com/stream".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let expected: &[u8] = b"[playlist]\n\
+                            File1=Track 1.mp3\n\
+                            Title1=Unknown Artist - Track 1\n\
+                            Length1=420\n\
+                            Genre1=Rock\n\
+                            \n\
+                            File2=http://somestream.com/stream\n\
+                            \n\
+                            NumberOfEntries=2\n\
+                            Version=2\n";
+    let opts = WriteOptions::default();
+
+    let mut outputs = vec![pls::to_bytes(&elems), pls::to_bytes_with(&elems, &opts)];
+    let mut writer = Writer::with_options(Vec::new(), opts.clone());
+    writer.extend(elems.iter());
+    outputs.push(writer.finish().unwrap());
+    for write in &[|e: &[PlaylistElement], to: &mut Vec<u8>| pls::write(e, to),
+                   |e: &[PlaylistElement], to: &mut Vec<u8>| pls::write_with(e, &WriteOptions::default(), to),
+                   |e: &[PlaylistElement], to: &mut Vec<u8>| pls::write_range_with(e, 0..e.len(), &WriteOptions::default(), to),
+                   |e: &[PlaylistElement], to: &mut Vec<u8>| pls::write_filtered_with(e, |_| true, &WriteOptions::default(), to),
+                   |e: &[PlaylistElement], to: &mut Vec<u8>| pls::write_indexed_with(&e.iter().cloned().enumerate().collect(), &WriteOptions::default(), to),
+                   |e: &[PlaylistElement], to: &mut Vec<u8>| pls::write_canonical_with(e, &WriteOptions::default(), to)] {
+        let mut buf = Vec::new();
+        write(&elems, &mut buf).unwrap();
+        outputs.push(buf);
+    }
+
+    for out in outputs {
+        assert_eq!(String::from_utf8(out).unwrap(), String::from_utf8(expected.to_vec()).unwrap());
+    }
+    assert_eq!(pls::to_bytes_with(&[] as &[PlaylistElement], &opts),
+               &b"[playlist]\n\
+                  NumberOfEntries=0\n\
+                  Version=2\n"[..]);
+}
+
+#[test]
+fn start_index() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Seconds(420),
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "Track 2.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let opts = WriteOptions::default().start_index(0).align_keys(true);
+    let out = pls::to_bytes_with(&elems, &opts);
+    assert_eq!(String::from_utf8(out.clone()).unwrap(),
+               "[playlist]\n\
+                File0  =Track 1.mp3\n\
+                Length0=420\n\
+                \n\
+                File1  =Track 2.mp3\n\
+                \n\
+                NumberOfEntries=2\n\
+                Version=2\n");
+
+    let mut writer = Writer::with_options(Vec::new(), opts);
+    writer.extend(elems.iter());
+    assert_eq!(writer.finish().unwrap(), out);
+
+    let out = pls::to_bytes_with(&elems, &WriteOptions::default().start_index(9));
+    assert!(pls::parse(&mut &out[..]).is_err());
+    assert_eq!(pls::parse_with(&mut &out[..], &ParseOptions::default().require_contiguous_indices(false)).unwrap(), elems);
+
+    assert_eq!(pls::write_with(&elems, &WriteOptions::default().start_index(u64::MAX), &mut Vec::new()).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+    assert!(pls::write_with(&elems[..1], &WriteOptions::default().start_index(u64::MAX), &mut Vec::new()).is_ok());
+}
+
+#[test]
+fn key_order() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: Some("Unknown Artist - Track 1".to_string()),
+                     len: ElementLength::Seconds(420),
+                     genre: Some("Rock".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "Track 2.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: Some("Jazz".to_string()),
+                 }];
+    let out = pls::to_bytes_with(&elems, &WriteOptions::default().key_order([EntryKey::Length, EntryKey::Title, EntryKey::Length, EntryKey::Length]));
+    assert_eq!(String::from_utf8(out.clone()).unwrap(),
+               "[playlist]\n\
+                Length1=420\n\
+                Title1=Unknown Artist - Track 1\n\
+                File1=Track 1.mp3\n\
+                Genre1=Rock\n\
+                \n\
+                File2=Track 2.mp3\n\
+                Genre2=Jazz\n\
+                \n\
+                NumberOfEntries=2\n\
+                Version=2\n");
+    assert_eq!(pls::parse(&mut &out[..]).unwrap(), elems);
+
+    assert_eq!(pls::to_bytes_with(&elems, &WriteOptions::default().key_order(EntryKey::ALL)), pls::to_bytes(&elems));
+}
+
+#[test]
+fn byte_order_mark() {
+    let elems = [PlaylistElement {
+                     path: "Zażółć gęślą jaźń.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let opts = WriteOptions::default().byte_order_mark(true);
+
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &opts, &mut buf).unwrap();
+    assert!(buf.starts_with(b"\xEF\xBB\xBF[playlist]\n"));
+    assert_eq!(pls::parse(&mut &buf[..]).unwrap(), elems);
+
+    let mut writer = Writer::with_options(Vec::new(), opts.clone());
+    writer.push(&elems[0]).unwrap();
+    assert_eq!(writer.finish().unwrap(), buf);
+
+    let mut buf = Vec::new();
+    pls::write_with(&[] as &[PlaylistElement], &opts, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "\u{FEFF}[playlist]\nNumberOfEntries=0\nVersion=2\n");
+}
+
+#[test]
+fn footer_and_blank_lines() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: Some("Unknown Artist - Track 1".to_string()),
+                     len: ElementLength::Seconds(420),
+                     genre: None,
+                 },
+                 PlaylistElement {
+                     path: "http://somestream.com/stream".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let write = |opts: &WriteOptions| {
+        let mut writer = Writer::with_options(Vec::new(), opts.clone());
+        writer.extend(elems.iter());
+        let streamed = writer.finish().unwrap();
+        assert_eq!(pls::to_bytes_with(&elems, opts), streamed);
+        String::from_utf8(streamed).unwrap()
+    };
+
+    let compact = WriteOptions::default().blank_line_between_entries(false).emit_version(false);
+    assert_eq!(write(&compact),
+               "[playlist]\n\
+                File1=Track 1.mp3\n\
+                Title1=Unknown Artist - Track 1\n\
+                Length1=420\n\
+                File2=http://somestream.com/stream\n\
+                NumberOfEntries=2\n");
+    assert_eq!(pls::parse_str(&write(&compact)).unwrap(), elems);
+
+    let countless = WriteOptions::default().emit_number_of_entries(false);
+    assert_eq!(write(&countless),
+               "[playlist]\n\
+                File1=Track 1.mp3\n\
+                Title1=Unknown Artist - Track 1\n\
+                Length1=420\n\
+                \n\
+                File2=http://somestream.com/stream\n\
+                \n\
+                Version=2\n");
+    assert!(pls::parse_str(&write(&countless)).is_err());
+    assert_eq!(pls::parse_with(&mut write(&countless).as_bytes(), &ParseOptions::default().require_number_of_entries(false)).unwrap(),
+               elems);
+
+    let bare = countless.emit_version(false).blank_line_between_entries(false);
+    assert_eq!(pls::to_bytes_with(&[] as &[PlaylistElement], &bare), b"[playlist]\n");
+}
+
+#[test]
+fn crlf() {
+    let elems = [PlaylistElement {
+                     path: r"S:\M J U Z I K\pobrany\A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                     len: ElementLength::Seconds(218),
+                     genre: Some("Trap".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "http://somestream.com/stream".to_string(),
+                     title: None,
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    let opts = WriteOptions::default().line_ending(LineEnding::CrLf).align_keys(true).length_format(LengthFormat::Clock).byte_order_mark(true);
+
+    let written = pls::to_bytes_with(&elems, &opts);
+    assert_eq!(String::from_utf8(written.clone()).unwrap(),
+               "\u{FEFF}[playlist]\r\n\
+                File1  =S:\\M J U Z I K\\pobrany\\A-F-R-O & NGHTMRE - Stronger.mp3\r\n\
+                Title1 =A-F-R-O & NGHTMRE - Stronger\r\n\
+                Length1=3:38\r\n\
+                Genre1 =Trap\r\n\
+                \r\n\
+                File2  =http://somestream.com/stream\r\n\
+                \r\n\
+                NumberOfEntries=2\r\n\
+                Version=2\r\n");
+    assert_eq!(pls::parse_with(&mut &written[..], &ParseOptions::default().allow_clock_lengths(true)).unwrap(), elems);
+
+    let mut writer = Writer::with_options(Vec::new(), opts.clone());
+    writer.extend(elems.iter());
+    assert_eq!(writer.finish().unwrap(), written);
+
+    let default = WriteOptions::default().line_ending(LineEnding::CrLf);
+    assert_eq!(pls::parse_bytes(&pls::to_bytes_with(&elems, &default)).unwrap(), elems);
+    assert!(!pls::to_bytes_with(&elems, &default).windows(2).any(|w| w[0] != b'\r' && w[1] == b'\n'));
+    assert_eq!(pls::to_bytes_with(&[] as &[PlaylistElement], &default.emit_number_of_entries(false)),
+               b"[playlist]\r\nVersion=2\r\n");
+}
+
+#[test]
+fn to_bytes() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: None,
+                     len: ElementLength::Seconds(220),
+                     genre: None,
+                 }];
+    let opts = WriteOptions::default().length_format(LengthFormat::Clock).error_on_empty(true);
+
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &opts, &mut buf).unwrap();
+    assert_eq!(pls::to_bytes_with(&elems, &opts), buf);
+    assert!(String::from_utf8(buf).unwrap().contains("\nLength1=3:40\n"));
+
+    let borrowed = [PlaylistElementRef {
+                        path: "Track 1.mp3",
+                        title: None,
+                        len: ElementLength::Seconds(220),
+                        genre: None,
+                    }];
+    assert_eq!(pls::to_bytes(&borrowed), pls::to_bytes(&elems));
+
+    assert_eq!(pls::to_bytes_with(&[] as &[PlaylistElement], &opts), b"");
+    assert_eq!(pls::to_bytes(&[] as &[PlaylistElement]), b"[playlist]\nNumberOfEntries=0\nVersion=2\n");
+}
+
+#[test]
+fn validate_for_write() {
+    let elems = [PlaylistElement {
+                     path: "S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3".to_string(),
+                     title: Some("A-F-R-O & NGHTMRE - Stronger".to_string()),
+                     len: ElementLength::Seconds(230),
+                     genre: Some("Trap".to_string()),
+                 },
+                 PlaylistElement {
+                     path: " ".to_string(),
+                     title: Some("A-F-R-O - Animal Kingdom\nFile3=Injected.mp3".to_string()),
+                     len: ElementLength::Unknown,
+                     genre: Some(" Trap".to_string()),
+                 },
+                 PlaylistElement {
+                     path: "\"S:/M J U Z I K/pobrany/Broken.mp3\"".to_string(),
+                     title: Some("#1 Hit".to_string()),
+                     len: ElementLength::Seconds(124),
+                     genre: Some("Trap\r".to_string()),
+                 }];
+    let opts = WriteOptions::default().align_keys(true).byte_order_mark(true);
+
+    let preview = pls::validate_for_write(&elems, &opts).unwrap();
+    let mut buf = Vec::new();
+    pls::write_with(&elems, &opts, &mut buf).unwrap();
+    assert_eq!(preview.bytes, buf.len());
+    assert_eq!(preview.entries, 3);
+    assert_eq!(preview.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+               ["Empty path in key \"File2\"",
+                "Line break in key \"Title2\"",
+                "Value of key \"Genre2\" won't read back the same",
+                "Value of key \"File3\" won't read back the same",
+                "Value of key \"Title3\" won't read back the same",
+                "Line break in key \"Genre3\""]);
+
+    let preview = pls::validate_for_write(&elems[..1], &WriteOptions::default()).unwrap();
+    assert_eq!(preview.bytes, pls::to_bytes(&elems[..1]).len());
+    assert!(preview.warnings.is_empty());
+
+    assert_eq!(pls::validate_for_write(&[] as &[PlaylistElement], &WriteOptions::default()).unwrap().bytes,
+               pls::to_bytes(&[] as &[PlaylistElement]).len());
+    assert_eq!(pls::validate_for_write(&[] as &[PlaylistElement], &WriteOptions::default().error_on_empty(true)).unwrap_err().kind(),
+               io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn indexed() {
+    let elems: Vec<_> = (0..12)
+        .map(|i| {
+            PlaylistElement {
+                path: format!("S:/M J U Z I K/pobrany/{:02}.mp3", i),
+                title: None,
+                len: ElementLength::Seconds(i),
+                genre: None,
+            }
+        })
+        .collect();
+    let entries: BTreeMap<_, _> = elems.iter().enumerate().map(|(i, e)| (i * i * 1000 + 3, e.clone())).rev().collect();
+
+    let mut buf = Vec::new();
+    pls::write_indexed(&entries, &mut buf).unwrap();
+    let mut whole = Vec::new();
+    pls::write(&elems, &mut whole).unwrap();
+    assert_eq!(String::from_utf8(buf.clone()).unwrap(), String::from_utf8(whole).unwrap());
+    assert_eq!(pls::parse(&mut &buf[..]).unwrap(), elems);
+
+    let mut buf = Vec::new();
+    pls::write_indexed(&BTreeMap::<usize, PlaylistElementRef>::new(), &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "[playlist]\nNumberOfEntries=0\nVersion=2\n");
+}
+
+#[test]
+fn round_trip_corpus() {
+    let elem = |path: &str, title: Option<&str>, len, genre: Option<&str>| {
+        PlaylistElement {
+            path: path.to_string(),
+            title: title.map(str::to_string),
+            len,
+            genre: genre.map(str::to_string),
+        }
+    };
+    let corpus = [elem("S:/M J U Z I K/pobrany/A-F-R-O & NGHTMRE - Stronger.mp3", Some("A-F-R-O & NGHTMRE - Stronger"), ElementLength::Seconds(230), None),
+                  // An empty title or genre is written as an empty key, and reads back as present, unlike a missing one
+                  elem("Track 1.mp3", Some(""), ElementLength::Unknown, None),
+                  elem("Track 1.mp3", None, ElementLength::Unknown, Some("")),
+                  elem("Track 1.mp3", Some(""), ElementLength::Unknown, Some("")),
+                  elem("", None, ElementLength::Unknown, None),
+                  elem("Track 1.mp3", None, ElementLength::Seconds(0), None),
+                  elem("Track 1.mp3", None, ElementLength::Seconds(u64::MAX), None),
+                  elem(r"C:\Music\", Some(r"\"), ElementLength::Unknown, Some(r"Drum \ Bass")),
+                  elem(r"\\Server\Music\Track 1.mp3", Some("A=B: C"), ElementLength::Unknown, None),
+                  elem("http://127.0.0.1:8002/stream?id=1&format=mp3", Some("Radio: It's \"Live\""), ElementLength::Unknown, Some("Talk")),
+                  elem("file:///S:/M%20J%20U%20Z%20I%20K/Stronger.mp3", Some("[playlist]"), ElementLength::Unknown, Some("[Electronic")),
+                  elem("音楽/🎵.mp3", Some("\u{FEFF}Zażółć gęślą jaźń"), ElementLength::Seconds(124), Some("Muzyka\tpopularna")),
+                  elem("Track 1.mp3", Some("NumberOfEntries=0"), ElementLength::Unknown, None)];
+
+    pls::assert_round_trip(&[]);
+    pls::assert_round_trip(&corpus);
+    for elem in corpus.chunks(1) {
+        pls::assert_round_trip(elem);
+        assert_eq!(pls::validate_for_write(elem, &WriteOptions::default()).unwrap().warnings.len(),
+                   elem[0].path.is_empty() as usize,
+                   "{:?}",
+                   elem);
+    }
+}
+
+#[test]
+#[should_panic(expected = "read back as")]
+fn round_trip_trailing_nul() {
+    let elems = [PlaylistElement {
+                     path: "Track 1.mp3".to_string(),
+                     title: Some("Unknown Artist - Track 1\0".to_string()),
+                     len: ElementLength::Unknown,
+                     genre: None,
+                 }];
+    assert_eq!(pls::validate_for_write(&elems, &WriteOptions::default()).unwrap().warnings,
+               [pls::WriteWarning::Altered { key: "Title1".to_string() }]);
+    pls::assert_round_trip(&elems);
+}